@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+
+use crate::NotorError as Error;
+
+/// Site of the characters a document starts with, editors get sites starting at 1.
+pub const BASE_SITE: u32 = 0;
+
+/// Identifies a character of a [`TextDoc`], serialized as `[counter, site]`. The counter
+/// is a Lamport clock so characters inserted later compare greater.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemId(pub u64, pub u32);
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub id: ItemId,
+    pub ch: char,
+    /// Deleted characters are kept so that concurrent inserts next to them can be placed
+    pub deleted: bool,
+}
+
+/// Edit of a [`TextDoc`] made by one of its editors.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Op {
+    /// Inserts `ch` right after the character `origin` or at the start when it's `None`
+    Insert {
+        id: ItemId,
+        origin: Option<ItemId>,
+        ch: char,
+    },
+    Delete {
+        id: ItemId,
+    },
+}
+
+impl Op {
+    /// Site of the editor that made the edit, `None` for deletes which any editor can make.
+    pub fn site(&self) -> Option<u32> {
+        match self {
+            Op::Insert { id, .. } => Some(id.1),
+            Op::Delete { .. } => None,
+        }
+    }
+}
+
+/// Visible characters of a [`TextDoc`] at some point, see [`TextDoc::replace_since`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot(Vec<(ItemId, char)>);
+
+impl Snapshot {
+    pub fn text(&self) -> String {
+        self.0.iter().map(|&(_, ch)| ch).collect()
+    }
+}
+
+/// Text edited by several users at once as a replicated growable array. Applying the
+/// same operations in any order that respects their causality gives the same text.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TextDoc {
+    items: Vec<Item>,
+    /// Greatest counter seen so far
+    clock: u64,
+}
+
+impl TextDoc {
+    pub fn from_text(text: &str) -> Self {
+        let items: Vec<_> = text
+            .chars()
+            .zip(1..)
+            .map(|(ch, counter)| Item {
+                id: ItemId(counter, BASE_SITE),
+                ch,
+                deleted: false,
+            })
+            .collect();
+
+        TextDoc {
+            clock: items.len() as u64,
+            items,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.items
+            .iter()
+            .filter(|item| !item.deleted)
+            .map(|item| item.ch)
+            .collect()
+    }
+
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(
+            self.items
+                .iter()
+                .filter(|item| !item.deleted)
+                .map(|item| (item.id, item.ch))
+                .collect(),
+        )
+    }
+
+    /// Replaces the text with `text` as edits made by `site`, the changed part is found by
+    /// comparing both texts from either end. Returns the applied edits.
+    pub fn replace_text(&mut self, text: &str, site: u32) -> Vec<Op> {
+        self.replace_since(&mut self.snapshot(), text, site)
+    }
+
+    /// Changes the text from `base` to `text` as edits made by `site`, edits made since
+    /// `base` was taken are kept as if they were concurrent. `base` becomes the snapshot
+    /// of `text`. Returns the applied edits.
+    pub fn replace_since(&mut self, base: &mut Snapshot, text: &str, site: u32) -> Vec<Op> {
+        let chars: Vec<_> = text.chars().collect();
+
+        let start = base
+            .0
+            .iter()
+            .zip(&chars)
+            .take_while(|((_, base_ch), ch)| base_ch == *ch)
+            .count();
+        let end = base.0[start..]
+            .iter()
+            .rev()
+            .zip(chars[start..].iter().rev())
+            .take_while(|((_, base_ch), ch)| base_ch == *ch)
+            .count();
+        let replaced = start..base.0.len() - end;
+
+        let mut ops: Vec<_> = base.0[replaced.clone()]
+            .iter()
+            .map(|&(id, _)| Op::Delete { id })
+            .collect();
+        let mut origin = start.checked_sub(1).map(|i| base.0[i].0);
+        let mut inserted = vec![];
+        for &ch in &chars[start..chars.len() - end] {
+            let id = ItemId(self.clock + inserted.len() as u64 + 1, site);
+            ops.push(Op::Insert { id, origin, ch });
+            inserted.push((id, ch));
+            origin = Some(id);
+        }
+
+        // characters of the snapshot are never forgotten so the edits always apply, only
+        // deletes of characters deleted since have no effect
+        ops.retain(|op| matches!(self.apply(op), Ok(true)));
+        base.0.splice(replaced, inserted);
+        ops
+    }
+
+    fn position(&self, id: ItemId) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    /// Applies the edit, returns whether the document changed. Edits referring to
+    /// characters the document doesn't have yet are rejected.
+    pub fn apply(&mut self, op: &Op) -> Result<bool, Error> {
+        match *op {
+            Op::Insert { id, origin, ch } => {
+                if self.position(id).is_some() {
+                    return Ok(false);
+                }
+                let mut i = match origin {
+                    Some(origin) => self.position(origin).ok_or_else(|| unknown_item(origin))? + 1,
+                    None => 0,
+                };
+                // concurrent inserts at the same place are ordered newest first
+                while i < self.items.len() && self.items[i].id > id {
+                    i += 1;
+                }
+
+                self.items.insert(
+                    i,
+                    Item {
+                        id,
+                        ch,
+                        deleted: false,
+                    },
+                );
+                self.clock = self.clock.max(id.0);
+                Ok(true)
+            }
+            Op::Delete { id } => {
+                let i = self.position(id).ok_or_else(|| unknown_item(id))?;
+                let changed = !self.items[i].deleted;
+                self.items[i].deleted = true;
+                Ok(changed)
+            }
+        }
+    }
+}
+
+fn unknown_item(id: ItemId) -> Error {
+    Error::InvalidEdit(format!("unknown character [{}, {}]", id.0, id.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(counter: u64, site: u32, origin: Option<ItemId>, ch: char) -> Op {
+        Op::Insert {
+            id: ItemId(counter, site),
+            origin,
+            ch,
+        }
+    }
+
+    fn permutations(ops: &[Op]) -> Vec<Vec<Op>> {
+        if ops.len() <= 1 {
+            return vec![ops.to_vec()];
+        }
+        let mut all = vec![];
+        for i in 0..ops.len() {
+            let mut rest = ops.to_vec();
+            let first = rest.remove(i);
+            for mut perm in permutations(&rest) {
+                perm.insert(0, first.clone());
+                all.push(perm);
+            }
+        }
+        all
+    }
+
+    /// Texts after applying `ops` in every order that respects their causality.
+    fn texts_in_all_orders(base: &str, ops: &[Op]) -> Vec<String> {
+        permutations(ops)
+            .into_iter()
+            .filter_map(|order| {
+                let mut doc = TextDoc::from_text(base);
+                for op in &order {
+                    // orders inserting a character before its origin can't happen
+                    doc.apply(op).ok()?;
+                }
+                Some(doc.text())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn concurrent_inserts_at_same_origin_converge() {
+        let base = TextDoc::from_text("ab");
+        let a = base.items()[0].id;
+        let ops = [
+            insert(3, 1, Some(a), 'x'),
+            insert(3, 2, Some(a), 'y'),
+            insert(3, 3, Some(a), 'z'),
+        ];
+
+        let texts = texts_in_all_orders("ab", &ops);
+        assert_eq!(texts.len(), 6);
+        assert!(texts.iter().all(|text| text == "azyxb"), "{:?}", texts);
+    }
+
+    #[test]
+    fn concurrent_runs_at_start_converge() {
+        // both editors type two characters at the start of the document
+        let ops = [
+            insert(2, 1, None, 'a'),
+            insert(3, 1, Some(ItemId(2, 1)), 'b'),
+            insert(2, 2, None, 'x'),
+            insert(3, 2, Some(ItemId(2, 2)), 'y'),
+        ];
+
+        let texts = texts_in_all_orders("!", &ops);
+        assert_eq!(texts.len(), 6);
+        // runs stay together, the greater site goes first on ties
+        assert!(texts.iter().all(|text| text == "xyab!"), "{:?}", texts);
+    }
+
+    #[test]
+    fn later_insert_goes_before_concurrent_older_ones() {
+        let mut doc = TextDoc::from_text("a");
+        let a = doc.items()[0].id;
+        doc.apply(&insert(2, 1, Some(a), 'x')).unwrap();
+        doc.apply(&insert(3, 1, Some(ItemId(2, 1)), 'y')).unwrap();
+        // made without seeing `xy`, so it's newer only by its counter
+        doc.apply(&insert(5, 2, Some(a), 'n')).unwrap();
+
+        assert_eq!(doc.text(), "anxy");
+        assert_eq!(doc.clock(), 5);
+    }
+
+    #[test]
+    fn duplicate_ops_are_ignored() {
+        let mut doc = TextDoc::from_text("ab");
+        let a = doc.items()[0].id;
+        let op = insert(3, 1, Some(a), 'x');
+
+        assert!(doc.apply(&op).unwrap());
+        assert!(!doc.apply(&op).unwrap());
+        assert_eq!(doc.text(), "axb");
+
+        let delete = Op::Delete { id: a };
+        assert!(doc.apply(&delete).unwrap());
+        assert!(!doc.apply(&delete).unwrap());
+        assert_eq!(doc.text(), "xb");
+    }
+
+    #[test]
+    fn rejects_unknown_characters() {
+        let mut doc = TextDoc::from_text("ab");
+
+        assert!(matches!(
+            doc.apply(&Op::Delete { id: ItemId(9, 1) }),
+            Err(Error::InvalidEdit(_))
+        ));
+        assert!(matches!(
+            doc.apply(&insert(3, 1, Some(ItemId(9, 1)), 'x')),
+            Err(Error::InvalidEdit(_))
+        ));
+        assert_eq!(doc.text(), "ab");
+    }
+
+    #[test]
+    fn deleted_characters_still_place_inserts() {
+        let mut doc = TextDoc::from_text("abc");
+        let b = doc.items()[1].id;
+        doc.apply(&Op::Delete { id: b }).unwrap();
+        doc.apply(&insert(4, 1, Some(b), 'x')).unwrap();
+
+        assert_eq!(doc.text(), "axc");
+    }
+
+    #[test]
+    fn replace_text_round_trips_multibyte_text() {
+        let base = "zażółć gęślą jaźń 日本語";
+        let mut doc = TextDoc::from_text(base);
+
+        for text in &[
+            "zażółta gęś jaźń 日本",
+            "",
+            "🦀 ąę 日本語",
+            "🦀 ąę 日本語 ✓",
+            "ąę",
+        ] {
+            let mut replica = doc.clone();
+            let ops = doc.replace_text(text, 1);
+            assert_eq!(doc.text(), *text);
+
+            for op in &ops {
+                replica.apply(op).unwrap();
+            }
+            assert_eq!(replica.text(), *text);
+        }
+    }
+
+    #[test]
+    fn replace_text_only_touches_the_changed_part() {
+        let mut doc = TextDoc::from_text("gęś i kaczka");
+        let ops = doc.replace_text("gęś, kaczka", 1);
+
+        // ` i` becomes `,`
+        assert_eq!(
+            ops.iter()
+                .filter(|op| matches!(op, Op::Delete { .. }))
+                .count(),
+            2
+        );
+        assert_eq!(
+            ops.iter()
+                .filter(|op| matches!(op, Op::Insert { .. }))
+                .count(),
+            1
+        );
+        assert!(doc.replace_text("gęś, kaczka", 1).is_empty());
+    }
+
+    #[test]
+    fn replace_since_keeps_later_edits() {
+        let mut doc = TextDoc::from_text("ą b ć");
+        let mut base = doc.snapshot();
+        let mut replica = doc.clone();
+        let edits = doc.replace_text("ą b ć d", 1);
+
+        let ops = doc.replace_since(&mut base, "x b ć", BASE_SITE);
+
+        assert_eq!(doc.text(), "x b ć d");
+        assert_eq!(base.text(), "x b ć");
+        // the replaced `ą` is deleted once and `x` inserted
+        assert_eq!(ops.len(), 2);
+        for op in edits.iter().chain(&ops) {
+            replica.apply(op).unwrap();
+        }
+        assert_eq!(replica.text(), doc.text());
+    }
+
+    #[test]
+    fn replace_since_skips_characters_deleted_since() {
+        let mut doc = TextDoc::from_text("ą b ć");
+        let mut base = doc.snapshot();
+        doc.replace_text("ą ć", 1);
+
+        let ops = doc.replace_since(&mut base, "ć", BASE_SITE);
+
+        assert_eq!(doc.text(), "ć");
+        assert!(ops.iter().all(|op| matches!(op, Op::Delete { .. })));
+        assert_eq!(ops.len(), 2);
+        assert!(doc.replace_since(&mut base, "ć", BASE_SITE).is_empty());
+    }
+
+    #[test]
+    fn concurrent_replaces_converge() {
+        let base = TextDoc::from_text("ą b ć");
+        let mut left = base.clone();
+        let mut right = base.clone();
+
+        let left_ops = left.replace_text("ą x b ć", 1);
+        let right_ops = right.replace_text("ą b y ć", 2);
+        for op in &right_ops {
+            left.apply(op).unwrap();
+        }
+        for op in &left_ops {
+            right.apply(op).unwrap();
+        }
+
+        assert_eq!(left.text(), right.text());
+        assert_eq!(left.text(), "ą x b y ć");
+    }
+}
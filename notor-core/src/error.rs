@@ -9,10 +9,48 @@ pub enum NotorError {
     Utf8ConversionError(#[from] std::str::Utf8Error),
     #[error("invalid role `{0}`")]
     InvalidRole(String),
+    #[error("invalid theme `{0}`")]
+    InvalidTheme(String),
+    #[error("invalid language tag `{0}`")]
+    InvalidLanguage(String),
+    #[error("unknown time zone `{0}`")]
+    InvalidTimeZone(String),
+    #[error("invalid patch - {0}")]
+    InvalidPatch(String),
+    #[error("invalid tag - {0}")]
+    InvalidTag(String),
+    #[error("invalid color `{0}`, expected `#rgb` or `#rrggbb`")]
+    InvalidColor(String),
+    #[error("invalid search query - {0}")]
+    InvalidSearch(String),
+    #[error("invalid notebook - {0}")]
+    InvalidNotebook(String),
+    #[error("invalid share - {0}")]
+    InvalidShare(String),
+    #[error("invalid public link - {0}")]
+    InvalidPublicLink(String),
+    #[error("invalid webhook - {0}")]
+    InvalidWebhook(String),
+    #[error("invalid reminder - {0}")]
+    InvalidReminder(String),
+    #[error("invalid edit - {0}")]
+    InvalidEdit(String),
+    #[error("invalid link - {0}")]
+    InvalidLink(String),
+    #[error("invalid attachment - {0}")]
+    InvalidAttachment(String),
+    #[error("attachment quota of {quota} bytes exceeded, {used} bytes are already used")]
+    QuotaExceeded { used: i64, quota: i64 },
+    #[error("blob store error - {0}")]
+    BlobError(String),
+    #[error("notifier error - {0}")]
+    NotifierError(String),
     #[error("timestamp was invalid")]
     InvalidTimestamp,
     #[error("user is not authorized to access this page")]
     UnauthorizedAccess,
+    #[error("`username` `{claimed}` doesn't match the authenticated user `{username}`")]
+    OwnerMismatch { claimed: String, username: String },
     #[error("token verification failed - `{0}`")]
     TokenVerificationError(#[from] jsonwebtoken::errors::Error),
     #[error("no authentication header was provided")]
@@ -25,6 +63,8 @@ pub enum NotorError {
     AuthTokenExpired,
     #[error("provided password was invalid")]
     InvalidPassword,
+    #[error("failed to hash password - {0}")]
+    PasswordHashError(String),
     #[error("internal error - `{0}`")]
     InvalidHeaderInternalErr(#[from] warp::http::header::InvalidHeaderValue),
     #[error("internal error - `{0}`")]
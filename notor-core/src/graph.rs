@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Note or tag in the graph of connections between notes of a user.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GraphNode {
+    Note {
+        id: i32,
+        title: String,
+    },
+    Tag {
+        id: i32,
+        name: String,
+        color: Option<String>,
+    },
+}
+
+/// Connection between two nodes of the graph. Notes and tags are numbered separately,
+/// the kind of the edge tells which ids refer to notes.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GraphEdge {
+    /// The note `source` links the note `target` in its content
+    Link {
+        source: i32,
+        target: i32,
+    },
+    Tagging {
+        note_id: i32,
+        tag_id: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NodeRef {
+    Note(i32),
+    Tag(i32),
+}
+
+impl GraphNode {
+    fn node_ref(&self) -> NodeRef {
+        match self {
+            GraphNode::Note { id, .. } => NodeRef::Note(*id),
+            GraphNode::Tag { id, .. } => NodeRef::Tag(*id),
+        }
+    }
+}
+
+impl GraphEdge {
+    fn ends(&self) -> (NodeRef, NodeRef) {
+        match self {
+            GraphEdge::Link { source, target } => (NodeRef::Note(*source), NodeRef::Note(*target)),
+            GraphEdge::Tagging { note_id, tag_id } => {
+                (NodeRef::Note(*note_id), NodeRef::Tag(*tag_id))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Graph {
+    /// Keeps only nodes reachable from the note `note_id` through at most `depth` edges,
+    /// regardless of their direction, and edges between them.
+    pub fn neighbourhood(self, note_id: i32, depth: usize) -> Graph {
+        let mut adjacent: HashMap<NodeRef, Vec<NodeRef>> = HashMap::new();
+        for edge in &self.edges {
+            let (a, b) = edge.ends();
+            adjacent.entry(a).or_default().push(b);
+            adjacent.entry(b).or_default().push(a);
+        }
+
+        let root = NodeRef::Note(note_id);
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+        if self.nodes.iter().any(|node| node.node_ref() == root) {
+            reached.insert(root);
+            queue.push_back((root, 0));
+        }
+        while let Some((node, distance)) = queue.pop_front() {
+            if distance == depth {
+                continue;
+            }
+            for next in adjacent.get(&node).into_iter().flatten() {
+                if reached.insert(*next) {
+                    queue.push_back((*next, distance + 1));
+                }
+            }
+        }
+
+        Graph {
+            nodes: self
+                .nodes
+                .into_iter()
+                .filter(|node| reached.contains(&node.node_ref()))
+                .collect(),
+            edges: self
+                .edges
+                .into_iter()
+                .filter(|edge| {
+                    let (a, b) = edge.ends();
+                    reached.contains(&a) && reached.contains(&b)
+                })
+                .collect(),
+        }
+    }
+}
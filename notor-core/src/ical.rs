@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+
+const PRODUCT_ID: &str = "-//notor//notor//EN";
+const DATETIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Longest content line in octets, longer lines are folded
+const MAX_LINE_LEN: usize = 75;
+
+/// Note with a due date, listed as an event or a to-do in a calendar.
+#[derive(Debug, Clone)]
+pub struct CalendarEntry {
+    pub note_id: i32,
+    pub title: String,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+    pub due_at: DateTime<Utc>,
+    /// Pending reminder of the note, becomes an alarm
+    pub remind_at: Option<DateTime<Utc>>,
+    /// Names of the tags of the note
+    pub tags: Vec<String>,
+    /// Task list items of the note, notes with tasks are to-dos instead of events
+    pub tasks: i64,
+    pub tasks_done: i64,
+}
+
+fn datetime(datetime: &DateTime<Utc>) -> String {
+    datetime.format(DATETIME_FORMAT).to_string()
+}
+
+/// Escapes a TEXT value.
+fn text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends the content line to `out`, folding it into lines of at most 75 octets that
+/// continue after a space. Lines are only split between characters.
+fn push_line(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Renders the entries as an iCalendar (RFC 5545) feed named `name` generated at
+/// `generated`, `note_url` gives the link back to a note by its id.
+pub fn calendar<F: Fn(i32) -> String>(
+    name: &str,
+    entries: &[CalendarEntry],
+    generated: &DateTime<Utc>,
+    note_url: F,
+) -> String {
+    let mut out = String::new();
+    let mut line = |line: String| push_line(&mut out, &line);
+
+    line("BEGIN:VCALENDAR".into());
+    line("VERSION:2.0".into());
+    line(format!("PRODID:{}", PRODUCT_ID));
+    line("CALSCALE:GREGORIAN".into());
+    line("METHOD:PUBLISH".into());
+    line(format!("X-WR-CALNAME:{}", text(name)));
+
+    for entry in entries {
+        let component = if entry.tasks > 0 { "VTODO" } else { "VEVENT" };
+        let url = note_url(entry.note_id);
+
+        line(format!("BEGIN:{}", component));
+        line(format!("UID:note-{}@notor", entry.note_id));
+        // published feeds stamp entries with the time they were generated
+        line(format!("DTSTAMP:{}", datetime(generated)));
+        line(format!("CREATED:{}", datetime(&entry.created)));
+        if let Some(updated) = &entry.updated {
+            line(format!("LAST-MODIFIED:{}", datetime(updated)));
+        }
+        line(format!("SUMMARY:{}", text(&entry.title)));
+        line(format!("DESCRIPTION:{}", text(&url)));
+        line(format!("URL:{}", url));
+        if !entry.tags.is_empty() {
+            let tags: Vec<_> = entry.tags.iter().map(|tag| text(tag)).collect();
+            line(format!("CATEGORIES:{}", tags.join(",")));
+        }
+
+        if entry.tasks > 0 {
+            line(format!("DUE:{}", datetime(&entry.due_at)));
+            let status = if entry.tasks_done == entry.tasks {
+                "COMPLETED"
+            } else {
+                "NEEDS-ACTION"
+            };
+            line(format!("STATUS:{}", status));
+            line(format!(
+                "PERCENT-COMPLETE:{}",
+                entry.tasks_done * 100 / entry.tasks
+            ));
+        } else {
+            // without an end the event takes no time
+            line(format!("DTSTART:{}", datetime(&entry.due_at)));
+        }
+
+        if let Some(remind_at) = &entry.remind_at {
+            line("BEGIN:VALARM".into());
+            line("ACTION:DISPLAY".into());
+            line(format!("DESCRIPTION:{}", text(&entry.title)));
+            line(format!("TRIGGER;VALUE=DATE-TIME:{}", datetime(remind_at)));
+            line("END:VALARM".into());
+        }
+        line(format!("END:{}", component));
+    }
+
+    line("END:VCALENDAR".into());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry() -> CalendarEntry {
+        CalendarEntry {
+            note_id: 7,
+            title: "Plan".into(),
+            created: Utc.ymd(2021, 4, 1).and_hms(8, 0, 0),
+            updated: Some(Utc.ymd(2021, 4, 2).and_hms(9, 30, 0)),
+            due_at: Utc.ymd(2021, 5, 1).and_hms(10, 0, 0),
+            remind_at: None,
+            tags: vec![],
+            tasks: 0,
+            tasks_done: 0,
+        }
+    }
+
+    fn render(entries: &[CalendarEntry]) -> String {
+        let generated = Utc.ymd(2021, 4, 30).and_hms(12, 0, 0);
+        calendar("notor", entries, &generated, |id| {
+            format!("https://notor.example/web/notes/{}", id)
+        })
+    }
+
+    /// Content lines with folded continuations joined back.
+    fn unfold(ics: &str) -> Vec<String> {
+        ics.replace("\r\n ", "")
+            .split("\r\n")
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn lines_end_with_crlf() {
+        let ics = render(&[entry()]);
+
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(!ics.replace("\r\n", "").contains(['\r', '\n']));
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    }
+
+    #[test]
+    fn stamps_entries_with_generation_time() {
+        let lines = unfold(&render(&[entry()]));
+
+        assert!(lines.contains(&"DTSTAMP:20210430T120000Z".to_string()));
+        assert!(lines.contains(&"CREATED:20210401T080000Z".to_string()));
+        assert!(lines.contains(&"LAST-MODIFIED:20210402T093000Z".to_string()));
+    }
+
+    #[test]
+    fn folds_long_multibyte_lines() {
+        let mut entry = entry();
+        entry.title = "zażółć gęślą jaźń 日本語のメモ ".repeat(8);
+        let ics = render(&[entry.clone()]);
+
+        for line in ics.split("\r\n") {
+            assert!(
+                line.len() <= MAX_LINE_LEN,
+                "{} octets: {}",
+                line.len(),
+                line
+            );
+        }
+        // folding never splits a character and unfolding gives the value back
+        let summary = format!("SUMMARY:{}", entry.title);
+        assert!(unfold(&ics).contains(&summary));
+        assert!(
+            ics.split("\r\n")
+                .filter(|line| line.starts_with(' '))
+                .count()
+                > 3
+        );
+    }
+
+    #[test]
+    fn escapes_text_values() {
+        let mut entry = entry();
+        entry.title = "a, b; c\\d\ne".into();
+        entry.tags = vec!["work/x,y".into(), "semi;colon".into(), "plain".into()];
+        let lines = unfold(&render(&[entry]));
+
+        assert!(lines.contains(&r"SUMMARY:a\, b\; c\\d\ne".to_string()));
+        // commas separate categories so the ones in names are escaped
+        assert!(lines.contains(&r"CATEGORIES:work/x\,y,semi\;colon,plain".to_string()));
+    }
+
+    #[test]
+    fn notes_with_tasks_are_todos() {
+        let mut todo = entry();
+        todo.tasks = 4;
+        todo.tasks_done = 1;
+        todo.remind_at = Some(Utc.ymd(2021, 4, 30).and_hms(9, 0, 0));
+        let mut done = entry();
+        done.note_id = 8;
+        done.tasks = 2;
+        done.tasks_done = 2;
+        let lines = unfold(&render(&[todo, done, entry()]));
+
+        let count = |line: &str| lines.iter().filter(|l| *l == line).count();
+        assert_eq!(count("BEGIN:VTODO"), 2);
+        assert_eq!(count("BEGIN:VEVENT"), 1);
+        assert_eq!(count("DUE:20210501T100000Z"), 2);
+        assert_eq!(count("DTSTART:20210501T100000Z"), 1);
+        assert_eq!(count("PERCENT-COMPLETE:25"), 1);
+        assert_eq!(count("STATUS:NEEDS-ACTION"), 1);
+        assert_eq!(count("STATUS:COMPLETED"), 1);
+        assert_eq!(count("TRIGGER;VALUE=DATE-TIME:20210430T090000Z"), 1);
+        assert_eq!(count("UID:note-8@notor"), 1);
+        assert_eq!(count("URL:https://notor.example/web/notes/7"), 2);
+    }
+}
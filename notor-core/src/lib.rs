@@ -1,5 +1,16 @@
+pub mod crdt;
 mod error;
+pub mod graph;
+pub mod ical;
+pub mod links;
 pub mod models;
+pub mod patch;
+pub mod tasks;
 
 pub use error::NotorError;
+pub use graph::*;
+pub use ical::*;
+pub use links::*;
 pub use models::*;
+pub use patch::*;
+pub use tasks::*;
@@ -0,0 +1,372 @@
+use crate::NotorError as Error;
+use std::ops::Range;
+
+const LINK_START: &str = "[[";
+const LINK_END: &str = "]]";
+const ID_PREFIX: char = '#';
+const IMAGE_START: &str = "![";
+const IMAGE_TARGET: &str = "](";
+const IMAGE_END: char = ')';
+const ATTACHMENT_SCHEME: &str = "attachment:";
+
+/// Target of a wiki-style link in the content of a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WikiLink {
+    /// `[[#123]]` links the note with id 123
+    Id(i32),
+    /// `[[Note title]]` links notes of the same owner titled `Note title`
+    Title(String),
+}
+
+impl WikiLink {
+    fn parse(inner: &str) -> Option<Self> {
+        let inner = inner.trim();
+        if inner.is_empty() || inner.contains(['[', ']', '\n']) {
+            return None;
+        }
+
+        match inner.strip_prefix(ID_PREFIX).map(str::parse) {
+            Some(Ok(id)) => Some(WikiLink::Id(id)),
+            _ => Some(WikiLink::Title(inner.to_string())),
+        }
+    }
+}
+
+/// Attachment of the note shown by a markdown image in its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachmentRef {
+    /// `![alt](attachment:12)` shows the attachment with id 12
+    Id(i32),
+    /// `![alt](attachment:diagram.png)` shows the attachment named `diagram.png`
+    Name(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageRef {
+    pub alt: String,
+    pub attachment: AttachmentRef,
+}
+
+impl ImageRef {
+    fn parse(alt: &str, target: &str) -> Option<Self> {
+        let target = target.trim().strip_prefix(ATTACHMENT_SCHEME)?;
+        if alt.contains(['[', ']', '\n']) || target.is_empty() || target.contains('\n') {
+            return None;
+        }
+
+        Some(ImageRef {
+            alt: alt.trim().to_string(),
+            attachment: match target.parse() {
+                Ok(id) => AttachmentRef::Id(id),
+                Err(_) => AttachmentRef::Name(target.to_string()),
+            },
+        })
+    }
+}
+
+/// Part of note content, either plain text, a link or an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentPart<'a> {
+    Text(&'a str),
+    /// `text` is the link as written, including the brackets
+    Link {
+        text: &'a str,
+        link: WikiLink,
+    },
+    Image {
+        text: &'a str,
+        image: ImageRef,
+    },
+}
+
+/// Finds all `[[...]]` links in `content` with their byte ranges.
+pub fn wiki_links(content: &str) -> Vec<(Range<usize>, WikiLink)> {
+    let mut links = vec![];
+    let mut offset = 0;
+
+    while let Some(start) = content[offset..].find(LINK_START).map(|i| i + offset) {
+        let inner_start = start + LINK_START.len();
+        let end = match content[inner_start..].find(LINK_END) {
+            Some(i) => inner_start + i,
+            None => break,
+        };
+
+        match WikiLink::parse(&content[inner_start..end]) {
+            Some(link) => {
+                links.push((start..end + LINK_END.len(), link));
+                offset = end + LINK_END.len();
+            }
+            // `[[[title]]` still contains a link
+            None => offset = start + 1,
+        }
+    }
+
+    links
+}
+
+/// Finds all markdown images showing attachments in `content` with their byte ranges.
+/// Images of other urls are left out.
+pub fn attachment_images(content: &str) -> Vec<(Range<usize>, ImageRef)> {
+    let mut images = vec![];
+    let mut offset = 0;
+
+    while let Some(start) = content[offset..].find(IMAGE_START).map(|i| i + offset) {
+        let alt_start = start + IMAGE_START.len();
+        let parsed = content[alt_start..].find(IMAGE_TARGET).and_then(|i| {
+            let target_start = alt_start + i + IMAGE_TARGET.len();
+            let end = target_start + content[target_start..].find(IMAGE_END)?;
+            ImageRef::parse(
+                &content[alt_start..alt_start + i],
+                &content[target_start..end],
+            )
+            .map(|image| (end + 1, image))
+        });
+
+        match parsed {
+            Some((end, image)) => {
+                images.push((start..end, image));
+                offset = end;
+            }
+            None => offset = alt_start,
+        }
+    }
+
+    images
+}
+
+/// Splits `content` into text, links and images for rendering.
+pub fn content_parts(content: &str) -> Vec<ContentPart<'_>> {
+    let mut spans: Vec<_> = wiki_links(content)
+        .into_iter()
+        .map(|(range, link)| {
+            let text = &content[range.clone()];
+            (range, ContentPart::Link { text, link })
+        })
+        .chain(
+            attachment_images(content)
+                .into_iter()
+                .map(|(range, image)| {
+                    let text = &content[range.clone()];
+                    (range, ContentPart::Image { text, image })
+                }),
+        )
+        .collect();
+    spans.sort_by_key(|(range, _)| range.start);
+
+    let mut parts = vec![];
+    let mut last = 0;
+
+    for (range, part) in spans {
+        // a link inside the alt text of an image or the other way around
+        if range.start < last {
+            continue;
+        }
+        if range.start > last {
+            parts.push(ContentPart::Text(&content[last..range.start]));
+        }
+        parts.push(part);
+        last = range.end;
+    }
+    if last < content.len() {
+        parts.push(ContentPart::Text(&content[last..]));
+    }
+
+    parts
+}
+
+/// Makes sure that `[[title]]` links the note titled `title`. Titles with brackets or
+/// looking like `#123` can't be linked by title.
+pub fn check_link_title(title: &str) -> Result<(), Error> {
+    match WikiLink::parse(title) {
+        Some(WikiLink::Title(parsed)) if parsed == title.trim() => Ok(()),
+        _ => Err(Error::InvalidLink(format!(
+            "title `{}` can't be used in a link",
+            title
+        ))),
+    }
+}
+
+/// Rewrites `[[old_title]]` links in `content` to point to `new_title`. Returns `None`
+/// if there was nothing to rewrite, fails if `new_title` can't be linked.
+pub fn rewrite_title_links(
+    content: &str,
+    old_title: &str,
+    new_title: &str,
+) -> Result<Option<String>, Error> {
+    check_link_title(new_title)?;
+    let old = WikiLink::Title(old_title.trim().to_string());
+    let mut rewritten = String::with_capacity(content.len());
+    let mut last = 0;
+
+    for (range, link) in wiki_links(content) {
+        if link == old {
+            rewritten.push_str(&content[last..range.start]);
+            rewritten.push_str(LINK_START);
+            rewritten.push_str(new_title.trim());
+            rewritten.push_str(LINK_END);
+            last = range.end;
+        }
+    }
+
+    if last == 0 {
+        Ok(None)
+    } else {
+        rewritten.push_str(&content[last..]);
+        Ok(Some(rewritten))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title(title: &str) -> WikiLink {
+        WikiLink::Title(title.to_string())
+    }
+
+    #[test]
+    fn finds_links_with_byte_ranges() {
+        let content = "zażółć [[First]] and [[#12]] or [[ Spaced ]]";
+        let links = wiki_links(content);
+
+        assert_eq!(
+            links,
+            vec![
+                (11..20, title("First")),
+                (25..32, WikiLink::Id(12)),
+                (36..48, title("Spaced")),
+            ]
+        );
+        assert_eq!(&content[links[0].0.clone()], "[[First]]");
+        assert_eq!(&content[links[2].0.clone()], "[[ Spaced ]]");
+    }
+
+    #[test]
+    fn skips_invalid_links() {
+        assert!(wiki_links("[[]] [[  ]] [[a\nb]] [[unclosed").is_empty());
+        // an id that doesn't fit links by title
+        assert_eq!(
+            wiki_links("[[#99999999999]]"),
+            vec![(0..16, title("#99999999999"))]
+        );
+    }
+
+    #[test]
+    fn finds_link_after_extra_bracket() {
+        assert_eq!(wiki_links("[[[title]]"), vec![(1..10, title("title"))]);
+        assert_eq!(wiki_links("[[a]b]] [[c]]"), vec![(8..13, title("c"))]);
+    }
+
+    #[test]
+    fn finds_attachment_images() {
+        let content = "![plot](attachment:3) ![web](https://x.io/a.png) ![ż](attachment:a b.png)";
+        let images = attachment_images(content);
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].0, 0..21);
+        assert_eq!(images[0].1.attachment, AttachmentRef::Id(3));
+        assert_eq!(&content[images[1].0.clone()], "![ż](attachment:a b.png)");
+        assert_eq!(images[1].1.alt, "ż");
+        assert_eq!(
+            images[1].1.attachment,
+            AttachmentRef::Name("a b.png".into())
+        );
+    }
+
+    #[test]
+    fn skips_invalid_images() {
+        assert!(attachment_images("![a](attachment:) ![b](attachment:1").is_empty());
+        assert!(attachment_images("![a\nb](attachment:1)").is_empty());
+        // the image in the alt text is still found
+        let images = attachment_images("![![x](attachment:1)");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].0, 2..20);
+    }
+
+    #[test]
+    fn splits_content_into_parts() {
+        let content = "see [[Note]], ![img](attachment:1)!";
+
+        assert_eq!(
+            content_parts(content),
+            vec![
+                ContentPart::Text("see "),
+                ContentPart::Link {
+                    text: "[[Note]]",
+                    link: title("Note"),
+                },
+                ContentPart::Text(", "),
+                ContentPart::Image {
+                    text: "![img](attachment:1)",
+                    image: ImageRef {
+                        alt: "img".into(),
+                        attachment: AttachmentRef::Id(1),
+                    },
+                },
+                ContentPart::Text("!"),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_inside_image_alt_is_skipped() {
+        // brackets aren't allowed in the alt text so the link wins
+        let content = "![[[Note]]](attachment:1)";
+        let parts = content_parts(content);
+
+        assert_eq!(
+            parts,
+            vec![
+                ContentPart::Text("!["),
+                ContentPart::Link {
+                    text: "[[Note]]",
+                    link: title("Note"),
+                },
+                ContentPart::Text("](attachment:1)"),
+            ]
+        );
+        let joined: String = parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text(text) => *text,
+                ContentPart::Link { text, .. } | ContentPart::Image { text, .. } => *text,
+            })
+            .collect();
+        assert_eq!(joined, content);
+    }
+
+    #[test]
+    fn overlapping_link_and_image_keep_the_first() {
+        let content = "![a](attachment:[[Note]])";
+        let parts = content_parts(content);
+
+        assert_eq!(parts.len(), 1);
+        assert!(matches!(parts[0], ContentPart::Image { text, .. } if text == content));
+    }
+
+    #[test]
+    fn rewrites_title_links() {
+        let content = "[[Old]] and [[ Old ]] but not [[Older]] or [[#1]]";
+
+        assert_eq!(
+            rewrite_title_links(content, "Old", "New").unwrap(),
+            Some("[[New]] and [[New]] but not [[Older]] or [[#1]]".into())
+        );
+        assert_eq!(
+            rewrite_title_links("[[Other]]", "Old", "New").unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn refuses_titles_that_cant_be_linked() {
+        for new_title in &["a]]b", "[[a", "a]b", "#12", "  ", "a\nb"] {
+            assert!(matches!(
+                rewrite_title_links("[[Old]]", "Old", new_title),
+                Err(Error::InvalidLink(_))
+            ));
+        }
+        assert!(check_link_title(" Fine title ").is_ok());
+        assert!(check_link_title("#tag-like").is_ok());
+    }
+}
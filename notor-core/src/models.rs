@@ -1,19 +1,78 @@
 use crate::NotorError as Error;
-use chrono::{Datelike, NaiveDateTime, Timelike, Utc};
+use chrono::{DateTime, Datelike, FixedOffset, Offset, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub type NoteWithTags = (Note, Vec<Tag>);
 
+/// Maximum length of a BCP 47 language tag that we are willing to store
+pub const LANGUAGE_TAG_MAX_LEN: usize = 35;
+/// Separates segments of nested tag names like `work/projects/alpha`
+pub const TAG_SEPARATOR: char = '/';
+/// Maximum length of the full path of a tag
+pub const TAG_PATH_MAX_LEN: usize = 256;
+
+/// Timestamps are stored in UTC, `Tz` is only changed when presenting the note
+/// in the time zone of the viewer.
 #[derive(Serialize, Deserialize, Debug)]
-pub struct Note {
+#[serde(bound(
+    serialize = "Tz::Offset: std::fmt::Display",
+    deserialize = "DateTime<Tz>: Deserialize<'de>"
+))]
+pub struct Note<Tz: TimeZone = Utc> {
     pub id: i32,
     pub user_id: i32,
-    pub created: NaiveDateTime,
+    pub created: DateTime<Tz>,
     pub title: String,
     pub content: Option<String>,
+    /// Time of the last modification, `None` if the note was never edited
+    pub updated: Option<DateTime<Tz>>,
+    /// Id of the user who modified the note last
+    pub updated_by: Option<i32>,
+    /// Notebook the note is filed in, `None` for notes outside of any notebook
+    pub notebook_id: Option<i32>,
+    /// Pinned notes are listed before all other notes
+    pub pinned: bool,
+    pub favorite: bool,
+    /// Time the note was archived, archived notes are hidden from default listings
+    pub archived: Option<DateTime<Tz>>,
+    pub due_at: Option<DateTime<Tz>>,
+    /// Time the owner is reminded of the note
+    pub remind_at: Option<DateTime<Tz>>,
+    /// Time the reminder was sent, `None` while it's pending
+    pub reminded: Option<DateTime<Tz>>,
 }
 
 impl Note {
+    /// Converts timestamps to the local time of `tz`, keeping the offset that was in
+    /// effect at each of them so the result serializes to valid RFC 3339.
+    pub fn localize(self, tz: &Tz) -> Note<FixedOffset> {
+        Note {
+            id: self.id,
+            user_id: self.user_id,
+            created: localize(&self.created, tz),
+            title: self.title,
+            content: self.content,
+            updated: self.updated.map(|updated| localize(&updated, tz)),
+            updated_by: self.updated_by,
+            notebook_id: self.notebook_id,
+            pinned: self.pinned,
+            favorite: self.favorite,
+            archived: self.archived.map(|archived| localize(&archived, tz)),
+            due_at: self.due_at.map(|due_at| localize(&due_at, tz)),
+            remind_at: self.remind_at.map(|remind_at| localize(&remind_at, tz)),
+            reminded: self.reminded.map(|reminded| localize(&reminded, tz)),
+        }
+    }
+}
+
+pub fn localize(datetime: &DateTime<Utc>, tz: &Tz) -> DateTime<FixedOffset> {
+    let local = datetime.with_timezone(tz);
+    local.with_timezone(&local.offset().fix())
+}
+
+impl<Tz: TimeZone> Note<Tz> {
     pub fn created_datetime(&self) -> String {
         let created = &self.created;
         format!(
@@ -27,24 +86,648 @@ impl Note {
     }
 }
 
+/// The owner of a created note is always the authenticated user.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewNote {
-    pub username: String,
     pub title: String,
     pub content: Option<String>,
+    /// Notebook in which the note is created, ignored when updating a note
+    #[serde(default)]
+    pub notebook_id: Option<i32>,
+    /// Deprecated, only accepted so that older clients keep working. A value different
+    /// from the authenticated user is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+impl NewNote {
+    pub fn check_owner<S: AsRef<str>>(&self, username: S) -> Result<(), Error> {
+        check_owner(self.username.as_deref(), username.as_ref())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Tags are nested through their names, `name` is the full path of the tag and every
+/// prefix of it ending before a `/` names one of its ancestors.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Tag {
     pub id: i32,
     pub user_id: i32,
     pub name: String,
+    /// Hex color like `#a5556d` used to display the tag
+    pub color: Option<String>,
+    pub description: Option<String>,
 }
 
+impl Tag {
+    /// Last segment of the path
+    pub fn leaf(&self) -> &str {
+        self.name.rsplit(TAG_SEPARATOR).next().unwrap_or(&self.name)
+    }
+
+    pub fn parent_path(&self) -> Option<&str> {
+        self.name
+            .rsplit_once(TAG_SEPARATOR)
+            .map(|(parent, _)| parent)
+    }
+}
+
+/// Trims whitespace around the segments of a tag path, rejecting empty segments and
+/// paths that are too long.
+pub fn normalize_tag_path(path: &str) -> Result<String, Error> {
+    let segments: Vec<&str> = path.split(TAG_SEPARATOR).map(str::trim).collect();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(Error::InvalidTag(format!(
+            "`{}` contains an empty segment",
+            path
+        )));
+    }
+
+    let normalized = segments.join(&TAG_SEPARATOR.to_string());
+    if normalized.len() > TAG_PATH_MAX_LEN {
+        return Err(Error::InvalidTag(format!(
+            "path is longer than {} bytes",
+            TAG_PATH_MAX_LEN
+        )));
+    }
+
+    Ok(normalized)
+}
+
+/// Returns paths of all ancestors of a normalized `path` from the root down, followed
+/// by the path itself.
+pub fn tag_path_prefixes(path: &str) -> Vec<&str> {
+    path.match_indices(TAG_SEPARATOR)
+        .map(|(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+        .collect()
+}
+
+/// Comma separated tags that a note has to have, or must not have when prefixed with
+/// `-`, like `work/projects, -archive`. Every tag also matches its nested tags.
+#[derive(Debug, Default, PartialEq)]
+pub struct TagExpression {
+    pub required: Vec<String>,
+    pub excluded: Vec<String>,
+}
+
+impl std::str::FromStr for TagExpression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut expression = TagExpression::default();
+        for term in s.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+            match term.strip_prefix('-') {
+                Some(tag) => expression.excluded.push(normalize_tag_path(tag)?),
+                None => expression.required.push(normalize_tag_path(term)?),
+            }
+        }
+
+        Ok(expression)
+    }
+}
+
+/// Checks that `color` is a hex color in the `#rgb` or `#rrggbb` form.
+pub fn validate_color(color: &str) -> Result<(), Error> {
+    let valid = color.starts_with('#')
+        && matches!(color.len(), 4 | 7)
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidColor(color.to_string()))
+    }
+}
+
+/// Tag together with the number of notes it is attached to directly.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagWithCount {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub note_count: i64,
+}
+
+/// Tag together with its nested tags.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagNode {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub note_count: i64,
+    pub children: Vec<TagNode>,
+}
+
+/// Builds the tag hierarchy out of a flat list of tags. Siblings keep their relative
+/// order from `tags`, tags whose parent is missing from the list are placed at the top
+/// level.
+pub fn tag_tree(mut tags: Vec<TagWithCount>) -> Vec<TagNode> {
+    let positions: HashMap<i32, usize> = tags
+        .iter()
+        .enumerate()
+        .map(|(position, tag)| (tag.tag.id, position))
+        .collect();
+    // parents sort before their descendants
+    tags.sort_by(|a, b| a.tag.name.cmp(&b.tag.name));
+
+    fn insert(nodes: &mut Vec<TagNode>, tag: TagWithCount) {
+        let parent = nodes.iter_mut().find(|node| {
+            tag.tag.name.starts_with(&node.tag.name)
+                && tag.tag.name[node.tag.name.len()..].starts_with(TAG_SEPARATOR)
+        });
+        match parent {
+            Some(parent) => insert(&mut parent.children, tag),
+            None => nodes.push(TagNode {
+                tag: tag.tag,
+                note_count: tag.note_count,
+                children: vec![],
+            }),
+        }
+    }
+
+    fn restore_order(nodes: &mut [TagNode], positions: &HashMap<i32, usize>) {
+        nodes.sort_by_key(|node| positions[&node.tag.id]);
+        for node in nodes {
+            restore_order(&mut node.children, positions);
+        }
+    }
+
+    let mut tree = vec![];
+    for tag in tags {
+        insert(&mut tree, tag);
+    }
+    restore_order(&mut tree, &positions);
+    tree
+}
+
+/// The owner of a created tag is always the authenticated user.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NewTag {
     pub name: String,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Deprecated, only accepted so that older clients keep working. A value different
+    /// from the authenticated user is rejected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+impl NewTag {
+    pub fn validate(&self) -> Result<(), Error> {
+        match &self.color {
+            Some(color) => validate_color(color),
+            None => Ok(()),
+        }
+    }
+
+    pub fn check_owner<S: AsRef<str>>(&self, username: S) -> Result<(), Error> {
+        check_owner(self.username.as_deref(), username.as_ref())
+    }
+}
+
+fn check_owner(claimed: Option<&str>, username: &str) -> Result<(), Error> {
+    match claimed {
+        Some(claimed) if claimed != username => Err(Error::OwnerMismatch {
+            claimed: claimed.to_string(),
+            username: username.to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Folder of notes, notebooks can be nested in other notebooks of the same user.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Notebook {
+    pub id: i32,
+    pub user_id: i32,
+    /// `None` for top level notebooks
+    pub parent_id: Option<i32>,
+    pub created: DateTime<Utc>,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NewNotebook {
+    pub name: String,
+    #[serde(default)]
+    pub parent_id: Option<i32>,
+}
+
+/// Orders `notebooks` depth first so that every notebook directly follows its parent,
+/// pairing each with its depth in the tree. Siblings are sorted by name, notebooks
+/// whose parent is missing from the list are treated as top level ones.
+pub fn notebook_tree(mut notebooks: Vec<Notebook>) -> Vec<(usize, Notebook)> {
+    notebooks.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+
+    let ids: Vec<i32> = notebooks.iter().map(|notebook| notebook.id).collect();
+    let mut stack: Vec<(usize, Notebook)> = vec![];
+    let mut rest = vec![];
+    for notebook in notebooks.into_iter().rev() {
+        match notebook.parent_id {
+            Some(parent) if ids.contains(&parent) => rest.push(notebook),
+            _ => stack.push((0, notebook)),
+        }
+    }
+
+    let mut tree = vec![];
+    while let Some((depth, notebook)) = stack.pop() {
+        let (children, others) = rest
+            .into_iter()
+            .partition(|child: &Notebook| child.parent_id == Some(notebook.id));
+        rest = others;
+        // `rest` is kept in reverse order, so children are pushed last to first
+        stack.extend(children.into_iter().map(|child| (depth + 1, child)));
+        tree.push((depth, notebook));
+    }
+
+    tree
+}
+
+/// Named note query that is evaluated on every listing, so its results stay current.
+/// `query` holds the same filters as the `/notes` listing accepts.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SavedSearch {
+    pub id: i32,
+    pub user_id: i32,
+    pub created: DateTime<Utc>,
+    pub name: String,
+    pub query: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NewSavedSearch {
+    pub name: String,
+    pub query: serde_json::Value,
+}
+
+/// What a user a note is shared with can do with it. Editors can also do everything
+/// viewers can.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "share_permission", rename_all = "snake_case")]
+pub enum SharePermission {
+    /// Can read the note, its tags and attachments
+    Viewer,
+    /// Can also change the title and content and manage attachments
+    Editor,
+}
+
+impl AsRef<str> for SharePermission {
+    fn as_ref(&self) -> &str {
+        match self {
+            SharePermission::Viewer => "viewer",
+            SharePermission::Editor => "editor",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NoteShare {
+    pub note_id: i32,
+    pub user_id: i32,
+    pub username: String,
+    pub permission: SharePermission,
+    pub created: DateTime<Utc>,
+}
+
+/// Shares a note with the user `username`, replacing the permission if the note is
+/// already shared with them.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NewNoteShare {
     pub username: String,
+    pub permission: SharePermission,
+}
+
+/// Note of another user shared with the current one.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SharedNote {
+    #[serde(flatten)]
+    pub note: Note,
+    /// Username of the owner
+    pub owner: String,
+    pub permission: SharePermission,
+}
+
+/// Secret address of the calendar feed of a user listing due dates of their notes, served
+/// at `/calendar/<token>.ics` without logging in.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CalendarFeed {
+    pub user_id: i32,
+    pub token: String,
+    pub created: DateTime<Utc>,
+}
+
+/// Unguessable link giving anyone who knows it read access to a note without logging in.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PublicLink {
+    pub id: i32,
+    pub note_id: i32,
+    pub token: String,
+    pub created: DateTime<Utc>,
+    /// The link stops working after this time, `None` for links that don't expire
+    pub expires: Option<DateTime<Utc>>,
+    /// Whether a password has to be provided to view the note
+    pub protected: bool,
+    /// How many times the note was viewed through the link
+    pub views: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct NewPublicLink {
+    pub expires: Option<DateTime<Utc>>,
+    pub password: Option<String>,
+}
+
+impl NewPublicLink {
+    pub fn validate(&self) -> Result<(), Error> {
+        if matches!(self.expires, Some(expires) if expires <= Utc::now()) {
+            return Err(Error::InvalidPublicLink(
+                "expiry time has already passed".into(),
+            ));
+        }
+        if matches!(&self.password, Some(password) if password.is_empty()) {
+            return Err(Error::InvalidPublicLink("password is empty".into()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Password of a protected public link.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LinkPassword {
+    pub password: String,
+}
+
+/// Note as seen by visitors of a public link.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PublicNote {
+    pub title: String,
+    pub content: Option<String>,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+/// Content of a note saved at the end of a live editing session.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NoteRevision {
+    pub id: i32,
+    pub note_id: i32,
+    /// User who edited the note last in the session
+    pub user_id: i32,
+    pub created: DateTime<Utc>,
+    pub content: Option<String>,
+}
+
+/// Task list item like `- [ ] buy milk` in the content of a note.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NoteTask {
+    pub note_id: i32,
+    pub note_title: String,
+    /// Counts tasks of the note from the start of its content
+    pub position: i32,
+    pub text: String,
+    pub done: bool,
+}
+
+/// Pending reminder or due date of a note.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Reminder {
+    pub note_id: i32,
+    pub note_title: String,
+    pub due_at: Option<DateTime<Utc>>,
+    /// `None` when the note only has a due date
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+/// Longest a reminder can be snoozed by with `minutes`, a year
+pub const MAX_SNOOZE_MINUTES: i64 = 366 * 24 * 60;
+
+/// Postpones the reminder of a note by `minutes` from now or until `until`.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct ReminderSnooze {
+    pub minutes: Option<i64>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ReminderSnooze {
+    /// Returns the new time of the reminder.
+    pub fn remind_at(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, Error> {
+        let remind_at = match (self.minutes, self.until) {
+            (Some(minutes), None) if minutes > 0 && minutes <= MAX_SNOOZE_MINUTES => {
+                now + chrono::Duration::minutes(minutes)
+            }
+            (None, Some(until)) => until,
+            (Some(_), None) => {
+                return Err(Error::InvalidReminder(format!(
+                    "snoozing takes between 1 and {} minutes",
+                    MAX_SNOOZE_MINUTES
+                )))
+            }
+            _ => {
+                return Err(Error::InvalidReminder(
+                    "exactly one of `minutes` and `until` is required".into(),
+                ))
+            }
+        };
+        if remind_at <= now {
+            return Err(Error::InvalidReminder(
+                "reminders can't be snoozed into the past".into(),
+            ));
+        }
+
+        Ok(remind_at)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Note,
+    Tag,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventAction {
+    Created,
+    Updated,
+    Deleted,
+    Tagged,
+    Untagged,
+    /// The reminder of a note is due
+    Reminder,
+}
+
+/// Change of a note or tag, announced by the database to every server instance.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub action: EventAction,
+    pub id: i32,
+    /// Owner of the changed note or tag
+    pub user_id: i32,
+    /// Name of the changed tag or the tag a note was tagged or untagged with
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Events webhooks can subscribe to, named `<kind>.<action>`
+pub const WEBHOOK_EVENTS: &[&str] = &[
+    "note.created",
+    "note.updated",
+    "note.deleted",
+    "note.tagged",
+    "note.untagged",
+    REMINDER_EVENT,
+    "tag.created",
+    "tag.updated",
+    "tag.deleted",
+];
+/// Event of deliveries sent with the test button
+pub const WEBHOOK_TEST_EVENT: &str = "ping";
+/// Event of deliveries sent when the reminder of a note is due
+pub const REMINDER_EVENT: &str = "note.reminder";
+
+/// Endpoint receiving signed POSTs when subscribed events happen. The body is signed with
+/// HMAC-SHA256 using `secret`, the signature is sent in the `X-Notor-Signature` header.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Webhook {
+    pub id: i32,
+    pub user_id: i32,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    /// Only events with this tag are delivered when set
+    pub tag: Option<String>,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NewWebhook {
+    pub url: String,
+    pub events: Vec<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl NewWebhook {
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(Error::InvalidWebhook(format!(
+                "`{}` is not an http or https url",
+                self.url
+            )));
+        }
+        if self.events.is_empty() {
+            return Err(Error::InvalidWebhook("no events were selected".into()));
+        }
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|event| !WEBHOOK_EVENTS.contains(&event.as_str()))
+        {
+            return Err(Error::InvalidWebhook(format!("unknown event `{}`", event)));
+        }
+        if matches!(&self.tag, Some(tag) if tag.is_empty()) {
+            return Err(Error::InvalidWebhook("tag is empty".into()));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry
+    Pending,
+    Succeeded,
+    /// Gave up after the last retry
+    Failed,
+}
+
+impl AsRef<str> for DeliveryStatus {
+    fn as_ref(&self) -> &str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Succeeded => "succeeded",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Entry of the delivery log of a webhook.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// Status code of the last attempt if the receiver responded
+    pub response_status: Option<i32>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub created: DateTime<Utc>,
+    pub next_attempt: Option<DateTime<Utc>>,
+    pub delivered: Option<DateTime<Utc>>,
+}
+
+/// File uploaded to a note. The content is kept in a blob store under [`Attachment::blob_key`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub id: i32,
+    pub note_id: i32,
+    pub user_id: i32,
+    pub created: DateTime<Utc>,
+    pub filename: String,
+    /// Type detected from the content, the type claimed by the client isn't trusted
+    pub content_type: String,
+    /// Size in bytes
+    pub size: i64,
+}
+
+impl Attachment {
+    pub fn blob_key(&self) -> String {
+        format!("{}/{}", self.user_id, self.id)
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+}
+
+/// Leading bytes of file formats recognized by [`sniff_content_type`]
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+];
+
+/// Detects the type of `data` from its leading bytes. Unrecognized content is served as
+/// plain text when it's valid UTF-8, which also keeps markup like html or svg inert.
+pub fn sniff_content_type(data: &[u8]) -> &'static str {
+    if let Some((_, content_type)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+    {
+        return content_type;
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+
+    if std::str::from_utf8(data).is_ok() {
+        "text/plain; charset=utf-8"
+    } else {
+        "application/octet-stream"
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -78,6 +761,14 @@ impl std::str::FromStr for UserRole {
     }
 }
 
+impl UserRole {
+    /// Whether a user with this role can use routes requiring `required`, admins can use
+    /// all of them.
+    pub fn allows(&self, required: &UserRole) -> bool {
+        self == required || *self == UserRole::Admin
+    }
+}
+
 impl AsRef<str> for UserRole {
     fn as_ref(&self) -> &str {
         match self {
@@ -90,7 +781,7 @@ impl AsRef<str> for UserRole {
 #[derive(Deserialize, Serialize, Debug)]
 pub struct User {
     pub id: i32,
-    pub created: NaiveDateTime,
+    pub created: DateTime<Utc>,
     pub username: String,
     pub email: String,
     pub pass: String,
@@ -105,6 +796,104 @@ pub struct NewUser {
     pub role: UserRole,
 }
 
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default, sqlx::Type)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "user_theme", rename_all = "kebab-case")]
+pub enum Theme {
+    /// Follows `prefers-color-scheme` and `prefers-contrast` of the browser
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl std::str::FromStr for Theme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Theme::Auto),
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "high-contrast" => Ok(Theme::HighContrast),
+            theme => Err(Error::InvalidTheme(theme.to_string())),
+        }
+    }
+}
+
+impl AsRef<str> for Theme {
+    fn as_ref(&self) -> &str {
+        match self {
+            Theme::Auto => "auto",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::HighContrast => "high-contrast",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UserSettings {
+    pub user_id: i32,
+    pub theme: Theme,
+    /// Preferred language of the interface, when missing the language is negotiated
+    /// from the `Accept-Language` header of the browser.
+    pub language: Option<String>,
+    /// IANA name of the time zone in which timestamps are displayed to the user
+    pub timezone: String,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            user_id: 0,
+            theme: Theme::default(),
+            language: None,
+            timezone: default_timezone(),
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NewUserSettings {
+    #[serde(default)]
+    pub theme: Theme,
+    pub language: Option<String>,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+fn default_timezone() -> String {
+    Tz::UTC.name().to_string()
+}
+
+impl NewUserSettings {
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(language) = &self.language {
+            let valid = !language.is_empty()
+                && language.len() <= LANGUAGE_TAG_MAX_LEN
+                && language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !valid {
+                return Err(Error::InvalidLanguage(language.to_string()));
+            }
+        }
+
+        self.timezone
+            .parse::<Tz>()
+            .map(|_| ())
+            .map_err(|_| Error::InvalidTimeZone(self.timezone.clone()))
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct JsonAuth {
     pub username: String,
@@ -136,3 +925,119 @@ impl Claims {
         self.exp < Utc::now().timestamp()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(id: i32, name: &str) -> TagWithCount {
+        TagWithCount {
+            tag: Tag {
+                id,
+                user_id: 1,
+                name: name.to_string(),
+                color: None,
+                description: None,
+            },
+            note_count: 0,
+        }
+    }
+
+    /// Renders the tree as `name(children)` so that whole trees compare at once.
+    fn render(nodes: &[TagNode]) -> String {
+        nodes
+            .iter()
+            .map(|node| {
+                if node.children.is_empty() {
+                    node.tag.name.clone()
+                } else {
+                    format!("{}({})", node.tag.name, render(&node.children))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn normalizes_tag_paths() {
+        assert_eq!(normalize_tag_path("work").unwrap(), "work");
+        assert_eq!(
+            normalize_tag_path(" work / projects/x ").unwrap(),
+            "work/projects/x"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_tag_paths() {
+        for path in ["", " ", "/work", "work/", "work//x", "work/ /x"] {
+            assert!(
+                matches!(normalize_tag_path(path), Err(Error::InvalidTag(_))),
+                "{:?}",
+                path
+            );
+        }
+
+        let long = "a".repeat(TAG_PATH_MAX_LEN + 1);
+        assert!(matches!(
+            normalize_tag_path(&long),
+            Err(Error::InvalidTag(_))
+        ));
+        assert!(normalize_tag_path(&long[1..]).is_ok());
+    }
+
+    #[test]
+    fn lists_tag_path_prefixes() {
+        assert_eq!(tag_path_prefixes("work"), ["work"]);
+        assert_eq!(
+            tag_path_prefixes("work/projects/x"),
+            ["work", "work/projects", "work/projects/x"]
+        );
+    }
+
+    #[test]
+    fn parses_tag_expressions() {
+        let expression: TagExpression = " work / projects, -archive,, ".parse().unwrap();
+        assert_eq!(
+            expression,
+            TagExpression {
+                required: vec!["work/projects".to_string()],
+                excluded: vec!["archive".to_string()],
+            }
+        );
+
+        assert_eq!(
+            "".parse::<TagExpression>().unwrap(),
+            TagExpression::default()
+        );
+        assert!("work, -".parse::<TagExpression>().is_err());
+        assert!("work//x".parse::<TagExpression>().is_err());
+    }
+
+    #[test]
+    fn builds_tag_tree() {
+        let tree = tag_tree(vec![
+            tag(1, "work/b"),
+            tag(2, "home"),
+            tag(3, "work"),
+            tag(4, "work/a"),
+            tag(5, "work/a/x"),
+        ]);
+
+        assert_eq!(render(&tree), "home work(work/b work/a(work/a/x))");
+    }
+
+    #[test]
+    fn places_tags_with_missing_parent_at_top_level() {
+        let tree = tag_tree(vec![tag(1, "work/a/x"), tag(2, "work/b"), tag(3, "home")]);
+
+        assert_eq!(render(&tree), "work/a/x work/b home");
+    }
+
+    #[test]
+    fn does_not_nest_tags_sharing_a_name_prefix() {
+        // `work-x` sorts between `work` and `work/a`
+        let tree = tag_tree(vec![tag(1, "work"), tag(2, "work-x"), tag(3, "work/a")]);
+
+        assert_eq!(render(&tree), "work(work/a) work-x");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::models::validate_color;
+use crate::NotorError as Error;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Partial update of a note following JSON Merge Patch (RFC 7396) semantics. A field
+/// missing from the patch is left untouched, a field set to `null` is removed and any
+/// other value replaces the current one.
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub struct NotePatch {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub content: Option<Option<String>>,
+    /// Names of all tags the note should have after the update, tags that don't exist
+    /// yet are created.
+    #[serde(default, deserialize_with = "present")]
+    pub tags: Option<Option<Vec<String>>>,
+    /// Moves the note to another notebook, `null` takes it out of its notebook.
+    #[serde(default, deserialize_with = "present")]
+    pub notebook_id: Option<Option<i32>>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+    #[serde(default)]
+    pub favorite: Option<bool>,
+    #[serde(default, deserialize_with = "present")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    /// Setting a new time makes a sent reminder pending again.
+    #[serde(default, deserialize_with = "present")]
+    pub remind_at: Option<Option<DateTime<Utc>>>,
+    /// Line based edits of the content for large notes, applied to the current content
+    /// instead of replacing it. Can't be combined with `content`.
+    #[serde(default)]
+    pub content_patch: Vec<LinePatch>,
+}
+
+/// Partial update of a tag with the same semantics as [`NotePatch`].
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct TagPatch {
+    /// New full path of the tag, nested tags are moved along with it.
+    #[serde(default, deserialize_with = "present")]
+    pub name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub color: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub description: Option<Option<String>>,
+}
+
+impl TagPatch {
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(None) = self.name {
+            return Err(Error::InvalidPatch("tag name can't be removed".into()));
+        }
+        if let Some(Some(color)) = &self.color {
+            validate_color(color)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Merges the tag into the tag `target_id`.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TagMerge {
+    pub target_id: i32,
+}
+
+/// Replaces lines `start..end` (zero based, end exclusive) of the content with `lines`.
+/// An empty range inserts `lines` before `start`, no `lines` deletes the range.
+/// Lines are separated by `\n`, so content ending with a newline has an empty last line.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LinePatch {
+    pub start: usize,
+    pub end: usize,
+    #[serde(default)]
+    pub lines: Vec<String>,
+}
+
+/// Distinguishes a field explicitly set to `null` (`Some(None)`) from a missing field
+/// which falls back to `None` through `#[serde(default)]`.
+fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl NotePatch {
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(None) = self.title {
+            return Err(Error::InvalidPatch("note title can't be removed".into()));
+        }
+        if self.content.is_some() && !self.content_patch.is_empty() {
+            return Err(Error::InvalidPatch(
+                "`content` and `content_patch` are mutually exclusive".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the patch changes how the owner organizes the note rather than the note
+    /// itself, which only the owner can do.
+    pub fn organizes(&self) -> bool {
+        self.tags.is_some()
+            || self.notebook_id.is_some()
+            || self.pinned.is_some()
+            || self.favorite.is_some()
+            || self.due_at.is_some()
+            || self.remind_at.is_some()
+    }
+
+    /// Returns the title after applying this patch to `current`.
+    pub fn patched_title(&self, current: String) -> String {
+        match &self.title {
+            Some(Some(title)) => title.clone(),
+            _ => current,
+        }
+    }
+
+    /// Returns the content after applying this patch to `current`.
+    pub fn patched_content(&self, current: Option<String>) -> Result<Option<String>, Error> {
+        if let Some(content) = &self.content {
+            return Ok(content.clone());
+        }
+        if self.content_patch.is_empty() {
+            return Ok(current);
+        }
+
+        apply_line_patches(current.as_deref().unwrap_or_default(), &self.content_patch).map(Some)
+    }
+}
+
+/// Applies all `patches` to `content`. Ranges refer to lines of the original content
+/// and must not overlap.
+pub fn apply_line_patches(content: &str, patches: &[LinePatch]) -> Result<String, Error> {
+    let mut lines: Vec<String> = if content.is_empty() {
+        vec![]
+    } else {
+        content.split('\n').map(String::from).collect()
+    };
+
+    let mut patches = patches.to_vec();
+    patches.sort_by_key(|patch| (patch.start, patch.end));
+
+    let mut prev_end = 0;
+    for patch in &patches {
+        if patch.start > patch.end || patch.end > lines.len() {
+            return Err(Error::InvalidPatch(format!(
+                "line range {}..{} is out of bounds of {} lines",
+                patch.start,
+                patch.end,
+                lines.len()
+            )));
+        }
+        if patch.start < prev_end {
+            return Err(Error::InvalidPatch(format!(
+                "line range {}..{} overlaps with a previous range",
+                patch.start, patch.end
+            )));
+        }
+        prev_end = patch.end;
+    }
+
+    // apply from the bottom so earlier ranges stay valid
+    for patch in patches.into_iter().rev() {
+        lines.splice(patch.start..patch.end, patch.lines);
+    }
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(start: usize, end: usize, lines: &[&str]) -> LinePatch {
+        LinePatch {
+            start,
+            end,
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn replaces_inserts_and_deletes_lines() {
+        let content = "a\nb\nc\nd";
+        let patches = [
+            patch(3, 4, &[]),
+            patch(1, 2, &["B1", "B2"]),
+            patch(0, 0, &["start"]),
+        ];
+
+        assert_eq!(
+            apply_line_patches(content, &patches).unwrap(),
+            "start\na\nB1\nB2\nc"
+        );
+    }
+
+    #[test]
+    fn adjacent_ranges_and_inserts_at_same_line() {
+        let content = "a\nb\nc";
+        let patches = [
+            patch(1, 1, &["x"]),
+            patch(1, 2, &["B"]),
+            patch(2, 3, &["C"]),
+        ];
+
+        assert_eq!(apply_line_patches(content, &patches).unwrap(), "a\nx\nB\nC");
+    }
+
+    #[test]
+    fn rejects_overlapping_ranges() {
+        let content = "a\nb\nc";
+        let patches = [patch(0, 2, &["x"]), patch(1, 3, &["y"])];
+
+        assert!(matches!(
+            apply_line_patches(content, &patches),
+            Err(Error::InvalidPatch(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_ranges_past_end() {
+        assert!(apply_line_patches("a\nb", &[patch(1, 3, &["x"])]).is_err());
+        assert!(apply_line_patches("a\nb", &[patch(3, 3, &["x"])]).is_err());
+        assert!(apply_line_patches("a\nb", &[patch(2, 1, &[])]).is_err());
+        // appending after the last line is allowed
+        assert_eq!(
+            apply_line_patches("a\nb", &[patch(2, 2, &["c"])]).unwrap(),
+            "a\nb\nc"
+        );
+    }
+
+    #[test]
+    fn preserves_trailing_newline() {
+        // the empty last line keeps the trailing newline
+        assert_eq!(
+            apply_line_patches("a\nb\n", &[patch(0, 1, &["A"])]).unwrap(),
+            "A\nb\n"
+        );
+        assert_eq!(
+            apply_line_patches("a\nb\n", &[patch(2, 3, &[])]).unwrap(),
+            "a\nb"
+        );
+    }
+
+    #[test]
+    fn patches_empty_content() {
+        assert_eq!(
+            apply_line_patches("", &[patch(0, 0, &["a", "b"])]).unwrap(),
+            "a\nb"
+        );
+        assert!(apply_line_patches("", &[patch(0, 1, &[])]).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_content_with_patches() {
+        let note_patch = NotePatch {
+            content: Some(Some("x".into())),
+            content_patch: vec![patch(0, 0, &["y"])],
+            ..Default::default()
+        };
+
+        assert!(note_patch.validate().is_err());
+    }
+}
@@ -0,0 +1,204 @@
+const FENCES: &[&str] = &["```", "~~~"];
+const TODO: &str = "[ ]";
+const DONE: &[&str] = &["[x]", "[X]"];
+
+/// Item of a GFM task list like `- [ ] buy milk` in the content of a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskItem {
+    pub done: bool,
+    pub text: String,
+    /// Byte offset of the `x` or space between the brackets
+    pub mark: usize,
+}
+
+/// Parses a list item starting with a checkbox, returns the offset of its mark within
+/// the line, whether it's checked and the text after it.
+fn parse_task_line(line: &str) -> Option<(usize, bool, &str)> {
+    let item = line.trim_start();
+    let marker_len = match item.chars().next()? {
+        '-' | '*' | '+' => 1,
+        _ => {
+            // ordered lists like `1.` or `1)`
+            let digits = item
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(item.len());
+            if digits == 0 || !item[digits..].starts_with(['.', ')']) {
+                return None;
+            }
+            digits + 1
+        }
+    };
+
+    let after_marker = &item[marker_len..];
+    let checkbox = after_marker.trim_start_matches([' ', '\t']);
+    if checkbox.len() == after_marker.len() {
+        return None;
+    }
+    let done = match checkbox.get(..TODO.len())? {
+        TODO => false,
+        mark if DONE.contains(&mark) => true,
+        _ => return None,
+    };
+    let text = &checkbox[TODO.len()..];
+    if !text.is_empty() && !text.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let mark = line.len() - checkbox.len() + 1;
+    Some((mark, done, text.trim()))
+}
+
+/// Finds all task list items in `content`, items inside fenced code blocks are left out.
+pub fn task_items(content: &str) -> Vec<TaskItem> {
+    let mut items = vec![];
+    let mut in_code = false;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        if FENCES
+            .iter()
+            .any(|fence| line.trim_start().starts_with(fence))
+        {
+            in_code = !in_code;
+        } else if !in_code {
+            if let Some((mark, done, text)) = parse_task_line(line) {
+                items.push(TaskItem {
+                    done,
+                    text: text.to_string(),
+                    mark: offset + mark,
+                });
+            }
+        }
+        offset += line.len();
+    }
+
+    items
+}
+
+/// Checks or unchecks the task at `position` among the items of `content`. Returns
+/// `None` if there are fewer tasks.
+pub fn toggle_task(content: &str, position: usize) -> Option<String> {
+    let item = task_items(content).into_iter().nth(position)?;
+    let mark = if item.done { " " } else { "x" };
+
+    let mut toggled = content.to_string();
+    toggled.replace_range(item.mark..item.mark + 1, mark);
+    Some(toggled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Toggles the task and checks that only the byte of its mark changed.
+    fn toggle_one(content: &str, position: usize) -> String {
+        let mark = task_items(content)[position].mark;
+        let toggled = toggle_task(content, position).unwrap();
+
+        assert_eq!(toggled.len(), content.len());
+        let changed: Vec<_> = content
+            .bytes()
+            .zip(toggled.bytes())
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(changed, vec![mark]);
+        toggled
+    }
+
+    #[test]
+    fn finds_task_items() {
+        let content = "- [ ] one\n* [x] two\n+ [X] three\n1. [ ] four\n12) [ ]  five \n";
+        let items = task_items(content);
+
+        let texts: Vec<_> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, vec!["one", "two", "three", "four", "five"]);
+        let done: Vec<_> = items.iter().map(|item| item.done).collect();
+        assert_eq!(done, vec![false, true, true, false, false]);
+        for item in &items {
+            assert!(matches!(
+                &content[item.mark..item.mark + 1],
+                " " | "x" | "X"
+            ));
+            assert_eq!(&content[item.mark - 1..item.mark], "[");
+        }
+    }
+
+    #[test]
+    fn skips_lines_that_arent_tasks() {
+        let content = "-[ ] no space\n- [ ]no space after\n- [y] other mark\n\
+                       1 [ ] no dot\n.) [ ] no digits\n[ ] no marker\n- [ \n-";
+
+        assert!(task_items(content).is_empty());
+    }
+
+    #[test]
+    fn empty_task_and_nested_items() {
+        let content = "- [ ]\n  - [x] nested\n\t1) [ ] tab";
+        let items = task_items(content);
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].text, "");
+        assert_eq!(items[1].mark, 11);
+        assert_eq!(items[2].text, "tab");
+    }
+
+    #[test]
+    fn skips_fenced_code_blocks() {
+        let content =
+            "- [ ] before\n```\n- [ ] code\n```\n~~~rust\n- [x] more code\n~~~\n- [ ] after";
+        let items = task_items(content);
+
+        let texts: Vec<_> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, vec!["before", "after"]);
+
+        let toggled = toggle_one(content, 1);
+        assert!(toggled.ends_with("- [x] after"));
+        assert!(toggled.contains("- [ ] code"));
+    }
+
+    #[test]
+    fn unclosed_fence_hides_the_rest() {
+        assert_eq!(task_items("- [ ] a\n```\n- [ ] b").len(), 1);
+    }
+
+    #[test]
+    fn offsets_after_multibyte_text() {
+        let content = "日本語のメモ\n- [ ] 買い物 🛒\n\u{3000}1) [x] 完了\n- [ ] ż";
+        let items = task_items(content);
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].text, "買い物 🛒");
+        assert_eq!(items[1].text, "完了");
+        assert_eq!(&content[items[0].mark..items[0].mark + 1], " ");
+        assert_eq!(&content[items[1].mark..items[1].mark + 1], "x");
+
+        let toggled = toggle_one(content, 0);
+        assert!(toggled.contains("- [x] 買い物 🛒"));
+        let toggled = toggle_one(&toggled, 1);
+        assert!(toggled.contains("1) [ ] 完了"));
+        let toggled = toggle_one(&toggled, 2);
+        assert!(toggled.ends_with("- [x] ż"));
+    }
+
+    #[test]
+    fn toggles_back_and_forth() {
+        let content = "- [X] upper\n1) [ ] ordered\n";
+
+        let toggled = toggle_one(content, 0);
+        assert_eq!(toggled, "- [ ] upper\n1) [ ] ordered\n");
+        let toggled = toggle_one(&toggled, 1);
+        assert_eq!(toggled, "- [ ] upper\n1) [x] ordered\n");
+        assert_eq!(
+            toggle_one(&toggle_one(&toggled, 1), 0),
+            "- [x] upper\n1) [ ] ordered\n"
+        );
+    }
+
+    #[test]
+    fn toggle_out_of_range() {
+        assert_eq!(toggle_task("- [ ] only", 1), None);
+        assert_eq!(toggle_task("```\n- [ ] code\n```", 0), None);
+    }
+}
@@ -0,0 +1,14 @@
+CREATE TYPE user_theme AS ENUM ( 'auto', 'light', 'dark', 'high-contrast' );
+
+CREATE TABLE IF NOT EXISTS user_settings
+(
+    user_id     INT NOT NULL,
+    theme       user_theme NOT NULL DEFAULT 'auto',
+
+    PRIMARY KEY(user_id),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+-- existing timestamps were always written in UTC
+ALTER TABLE notes
+    ALTER COLUMN created TYPE TIMESTAMPTZ USING created AT TIME ZONE 'UTC';
+
+ALTER TABLE users
+    ALTER COLUMN created TYPE TIMESTAMPTZ USING created AT TIME ZONE 'UTC';
+
+ALTER TABLE user_settings
+    ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
@@ -0,0 +1,8 @@
+ALTER TABLE notes
+    ADD COLUMN updated      TIMESTAMPTZ,
+    ADD COLUMN updated_by   INT,
+
+    ADD CONSTRAINT fk_updated_by
+        FOREIGN KEY(updated_by)
+            REFERENCES users(id)
+            ON DELETE SET NULL;
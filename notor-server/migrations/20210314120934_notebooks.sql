@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS notebooks
+(
+    id          INT GENERATED ALWAYS AS IDENTITY,
+    user_id     INT NOT NULL,
+    parent_id   INT,
+    created     TIMESTAMPTZ NOT NULL,
+    name        VARCHAR(128) NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE,
+
+    CONSTRAINT fk_parent
+        FOREIGN KEY(parent_id)
+            REFERENCES notebooks(id)
+            ON DELETE CASCADE
+);
+
+ALTER TABLE notes
+    ADD COLUMN notebook_id  INT,
+
+    ADD CONSTRAINT fk_notebook
+        FOREIGN KEY(notebook_id)
+            REFERENCES notebooks(id)
+            ON DELETE SET NULL;
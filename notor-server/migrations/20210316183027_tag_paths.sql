@@ -0,0 +1,5 @@
+-- nested tags store their full path like `work/projects/alpha` in `name`
+ALTER TABLE tags
+    ALTER COLUMN name TYPE VARCHAR(256);
+
+CREATE INDEX IF NOT EXISTS tags_user_name ON tags ( user_id, name text_pattern_ops );
@@ -0,0 +1,50 @@
+-- links without a note or a tag can't be reached
+DELETE FROM notes_tags
+WHERE note_id IS NULL OR tag_id IS NULL;
+
+-- merge duplicate tags into the oldest one
+UPDATE notes_tags
+SET tag_id = keep.id
+FROM tags AS dup
+INNER JOIN (
+    SELECT MIN(id) AS id, user_id, name
+    FROM tags
+    GROUP BY user_id, name
+) AS keep ON keep.user_id = dup.user_id AND keep.name = dup.name
+WHERE notes_tags.tag_id = dup.id AND dup.id <> keep.id;
+
+DELETE FROM tags
+USING tags AS keep
+WHERE tags.user_id = keep.user_id AND tags.name = keep.name AND tags.id > keep.id;
+
+-- drop repeated links, including the ones created by merging tags above
+DELETE FROM notes_tags
+USING notes_tags AS keep
+WHERE notes_tags.note_id = keep.note_id
+    AND notes_tags.tag_id = keep.tag_id
+    AND notes_tags.ctid > keep.ctid;
+
+ALTER TABLE tags
+    ADD CONSTRAINT tags_user_name_key
+        UNIQUE(user_id, name),
+
+    DROP CONSTRAINT fk_user,
+    ADD CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE;
+
+ALTER TABLE notes_tags
+    ADD PRIMARY KEY(note_id, tag_id),
+
+    DROP CONSTRAINT fk_notes,
+    ADD CONSTRAINT fk_notes
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE,
+
+    DROP CONSTRAINT fk_tags,
+    ADD CONSTRAINT fk_tags
+        FOREIGN KEY(tag_id)
+            REFERENCES tags(id)
+            ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+ALTER TABLE tags
+    ADD COLUMN color        VARCHAR(7),
+    ADD COLUMN description  TEXT;
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS saved_searches
+(
+    id          INT GENERATED ALWAYS AS IDENTITY,
+    user_id     INT NOT NULL,
+    created     TIMESTAMPTZ NOT NULL,
+    name        VARCHAR(128) NOT NULL,
+    query       JSONB NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+-- full-text search of the note listing
+CREATE INDEX IF NOT EXISTS notes_fulltext ON notes
+    USING GIN ( to_tsvector('simple', title || ' ' || COALESCE(content, '')) );
@@ -0,0 +1,3 @@
+ALTER TABLE notes
+    ADD COLUMN pinned       BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN favorite     BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- time the note was archived, NULL for active notes
+ALTER TABLE notes
+    ADD COLUMN archived     TIMESTAMPTZ;
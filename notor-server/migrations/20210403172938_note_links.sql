@@ -0,0 +1,26 @@
+-- `[[title]]` and `[[#id]]` links between notes of the same user
+CREATE TABLE IF NOT EXISTS note_links (
+    source_id   INT NOT NULL,
+    target_id   INT NOT NULL,
+    PRIMARY KEY(source_id, target_id),
+    CONSTRAINT fk_source
+        FOREIGN KEY(source_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE,
+    CONSTRAINT fk_target
+        FOREIGN KEY(target_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX note_links_target_idx ON note_links(target_id);
+
+-- index links already present in notes
+INSERT INTO note_links ( source_id, target_id )
+SELECT DISTINCT source.id, target.id
+FROM notes AS source
+CROSS JOIN LATERAL regexp_matches(source.content, '\[\[([^\[\]\n]+)\]\]', 'g') AS link(m)
+INNER JOIN notes AS target
+    ON target.user_id = source.user_id
+    AND target.id <> source.id
+    AND ( target.title = btrim(link.m[1]) OR '#' || target.id = btrim(link.m[1]) );
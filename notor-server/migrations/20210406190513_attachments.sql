@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS attachments
+(
+    id              INT GENERATED ALWAYS AS IDENTITY,
+    note_id         INT NOT NULL,
+    user_id         INT NOT NULL,
+    created         TIMESTAMPTZ NOT NULL,
+    filename        VARCHAR(256) NOT NULL,
+    content_type    VARCHAR(128) NOT NULL,
+    size            BIGINT NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_note
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE,
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX attachments_note_idx ON attachments(note_id);
+
+-- total size of attachments a user can store, 100 MiB by default
+ALTER TABLE users
+    ADD COLUMN attachment_quota     BIGINT NOT NULL DEFAULT 104857600;
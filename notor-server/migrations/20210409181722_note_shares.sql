@@ -0,0 +1,24 @@
+CREATE TYPE share_permission AS ENUM ( 'viewer', 'editor' );
+
+-- notes shared by their owners with other users
+CREATE TABLE IF NOT EXISTS note_shares
+(
+    note_id     INT NOT NULL,
+    user_id     INT NOT NULL,
+    permission  share_permission NOT NULL,
+    created     TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(note_id, user_id),
+
+    CONSTRAINT fk_note
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE,
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX note_shares_user_idx ON note_shares(user_id);
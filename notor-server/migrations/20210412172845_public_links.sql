@@ -0,0 +1,21 @@
+-- links giving anyone who knows the token read access to a note
+CREATE TABLE IF NOT EXISTS public_links
+(
+    id          INT GENERATED ALWAYS AS IDENTITY,
+    note_id     INT NOT NULL,
+    token       VARCHAR(64) NOT NULL UNIQUE,
+    created     TIMESTAMPTZ NOT NULL,
+    expires     TIMESTAMPTZ,
+    -- argon2 hash of the password, NULL for links without one
+    password    TEXT,
+    views       BIGINT NOT NULL DEFAULT 0,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_note
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX public_links_note_idx ON public_links(note_id);
@@ -0,0 +1,23 @@
+-- content of notes saved after live editing sessions
+CREATE TABLE IF NOT EXISTS note_revisions
+(
+    id          INT GENERATED ALWAYS AS IDENTITY,
+    note_id     INT NOT NULL,
+    user_id     INT NOT NULL,
+    created     TIMESTAMPTZ NOT NULL,
+    content     TEXT,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_note
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE,
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX note_revisions_note_idx ON note_revisions(note_id);
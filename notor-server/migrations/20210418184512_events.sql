@@ -0,0 +1,62 @@
+-- changes of notes and tags are announced on the `notor_events` channel so that every
+-- server instance can forward them to event streams of their owners
+CREATE OR REPLACE FUNCTION notify_event() RETURNS TRIGGER AS $$
+DECLARE
+    changed RECORD;
+    action TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        action := 'deleted';
+    ELSIF TG_OP = 'UPDATE' THEN
+        changed := NEW;
+        action := 'updated';
+    ELSE
+        changed := NEW;
+        action := 'created';
+    END IF;
+
+    PERFORM pg_notify('notor_events', json_build_object(
+        'kind', TG_ARGV[0],
+        'action', action,
+        'id', changed.id,
+        'user_id', changed.user_id
+    )::text);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- tagging and untagging updates the note
+CREATE OR REPLACE FUNCTION notify_note_tags_event() RETURNS TRIGGER AS $$
+DECLARE
+    changed RECORD;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+    ELSE
+        changed := NEW;
+    END IF;
+
+    PERFORM pg_notify('notor_events', json_build_object(
+        'kind', 'note',
+        'action', 'updated',
+        'id', notes.id,
+        'user_id', notes.user_id
+    )::text)
+    FROM notes
+    WHERE notes.id = changed.note_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notes_events
+    AFTER INSERT OR UPDATE OR DELETE ON notes
+    FOR EACH ROW EXECUTE FUNCTION notify_event('note');
+
+CREATE TRIGGER tags_events
+    AFTER INSERT OR UPDATE OR DELETE ON tags
+    FOR EACH ROW EXECUTE FUNCTION notify_event('tag');
+
+CREATE TRIGGER notes_tags_events
+    AFTER INSERT OR DELETE ON notes_tags
+    FOR EACH ROW EXECUTE FUNCTION notify_note_tags_event();
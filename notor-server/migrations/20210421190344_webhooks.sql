@@ -0,0 +1,132 @@
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'succeeded', 'failed');
+
+CREATE TABLE IF NOT EXISTS webhooks
+(
+    id          INT GENERATED ALWAYS AS IDENTITY,
+    user_id     INT NOT NULL,
+    url         TEXT NOT NULL,
+    secret      VARCHAR(64) NOT NULL,
+    -- `<kind>.<action>` names of subscribed events
+    events      TEXT[] NOT NULL,
+    tag         VARCHAR(256),
+    created     TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX webhooks_user_idx ON webhooks(user_id);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries
+(
+    id              INT GENERATED ALWAYS AS IDENTITY,
+    webhook_id      INT NOT NULL,
+    event           VARCHAR(32) NOT NULL,
+    payload         JSONB NOT NULL,
+    status          webhook_delivery_status NOT NULL DEFAULT 'pending',
+    attempts        INT NOT NULL DEFAULT 0,
+    response_status INT,
+    error           TEXT,
+    created         TIMESTAMPTZ NOT NULL,
+    -- pending deliveries are attempted once this passes
+    next_attempt    TIMESTAMPTZ,
+    delivered       TIMESTAMPTZ,
+
+    PRIMARY KEY(id),
+
+    CONSTRAINT fk_webhook
+        FOREIGN KEY(webhook_id)
+            REFERENCES webhooks(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX webhook_deliveries_webhook_idx ON webhook_deliveries(webhook_id, created);
+CREATE INDEX webhook_deliveries_pending_idx ON webhook_deliveries(next_attempt)
+    WHERE status = 'pending';
+
+-- announces the event and queues deliveries to the webhooks of the owner subscribed to
+-- it, queueing in the same transaction as the change means no event is lost or
+-- delivered twice no matter how many server instances run
+CREATE OR REPLACE FUNCTION publish_event(
+    event_kind TEXT,
+    event_action TEXT,
+    item_id INT,
+    owner_id INT,
+    tag_name TEXT
+) RETURNS VOID AS $$
+DECLARE
+    event JSONB;
+    event_name TEXT := event_kind || '.' || event_action;
+BEGIN
+    event := jsonb_build_object(
+        'kind', event_kind,
+        'action', event_action,
+        'id', item_id,
+        'user_id', owner_id,
+        'tag', tag_name
+    );
+    PERFORM pg_notify('notor_events', event::text);
+
+    INSERT INTO webhook_deliveries ( webhook_id, event, payload, created, next_attempt )
+    SELECT
+        webhooks.id,
+        event_name,
+        event || jsonb_build_object('event', event_name, 'created', now()),
+        now(),
+        now()
+    FROM webhooks
+    WHERE webhooks.user_id = owner_id
+        AND event_name = ANY(webhooks.events)
+        AND ( webhooks.tag IS NULL OR webhooks.tag = tag_name );
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_event() RETURNS TRIGGER AS $$
+DECLARE
+    changed RECORD;
+    action TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        action := 'deleted';
+    ELSIF TG_OP = 'UPDATE' THEN
+        changed := NEW;
+        action := 'updated';
+    ELSE
+        changed := NEW;
+        action := 'created';
+    END IF;
+
+    -- only tags have a name
+    PERFORM publish_event(
+        TG_ARGV[0], action, changed.id, changed.user_id, to_jsonb(changed) ->> 'name'
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_note_tags_event() RETURNS TRIGGER AS $$
+DECLARE
+    changed RECORD;
+    action TEXT;
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        changed := OLD;
+        action := 'untagged';
+    ELSE
+        changed := NEW;
+        action := 'tagged';
+    END IF;
+
+    -- the tag is already gone when it's deleted along with its taggings
+    PERFORM publish_event('note', action, notes.id, notes.user_id, tags.name)
+    FROM notes
+    LEFT JOIN tags ON tags.id = changed.tag_id
+    WHERE notes.id = changed.note_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
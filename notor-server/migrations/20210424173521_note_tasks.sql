@@ -0,0 +1,31 @@
+-- GFM task list items like `- [ ] buy milk` found in the content of notes
+CREATE TABLE IF NOT EXISTS note_tasks
+(
+    note_id     INT NOT NULL,
+    -- counts tasks of the note from the start of its content
+    position    INT NOT NULL,
+    text        TEXT NOT NULL,
+    done        BOOLEAN NOT NULL,
+
+    PRIMARY KEY(note_id, position),
+
+    CONSTRAINT fk_note
+        FOREIGN KEY(note_id)
+            REFERENCES notes(id)
+            ON DELETE CASCADE
+);
+
+CREATE INDEX note_tasks_open_idx ON note_tasks(note_id) WHERE NOT done;
+
+-- index tasks already present in notes, unlike the server this doesn't skip code blocks
+-- which is corrected on the next edit of the note
+INSERT INTO note_tasks ( note_id, position, text, done )
+SELECT
+    notes.id,
+    ( row_number() OVER ( PARTITION BY notes.id ORDER BY line.n ) - 1 )::INT,
+    btrim(coalesce(task.m[2], ''), E' \t\r'),
+    task.m[1] <> ' '
+FROM notes
+CROSS JOIN LATERAL regexp_split_to_table(notes.content, E'\n') WITH ORDINALITY AS line(text, n)
+CROSS JOIN LATERAL regexp_match(line.text, '^\s*(?:[-*+]|\d+[.)])[ \t]+\[([ xX])\](\s.*)?$') AS task(m)
+WHERE task.m IS NOT NULL;
@@ -0,0 +1,22 @@
+ALTER TABLE notes
+    ADD COLUMN due_at       TIMESTAMPTZ,
+    ADD COLUMN remind_at    TIMESTAMPTZ,
+    -- time the reminder at `remind_at` was sent, NULL while it's pending
+    ADD COLUMN reminded     TIMESTAMPTZ;
+
+CREATE INDEX notes_pending_reminders_idx ON notes(remind_at)
+    WHERE remind_at IS NOT NULL AND reminded IS NULL;
+CREATE INDEX notes_due_idx ON notes(user_id, due_at) WHERE due_at IS NOT NULL;
+
+-- sending a reminder announces its own event instead of updating the note
+DROP TRIGGER notes_events ON notes;
+
+CREATE TRIGGER notes_events
+    AFTER INSERT OR DELETE ON notes
+    FOR EACH ROW EXECUTE FUNCTION notify_event('note');
+
+CREATE TRIGGER notes_update_events
+    AFTER UPDATE ON notes
+    FOR EACH ROW
+    WHEN ( ( to_jsonb(OLD) - 'reminded' ) IS DISTINCT FROM ( to_jsonb(NEW) - 'reminded' ) )
+    EXECUTE FUNCTION notify_event('note');
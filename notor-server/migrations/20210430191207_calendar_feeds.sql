@@ -0,0 +1,14 @@
+-- secret addresses of iCalendar feeds listing due dates of notes, one per user
+CREATE TABLE IF NOT EXISTS calendar_feeds
+(
+    user_id     INT NOT NULL,
+    token       VARCHAR(64) NOT NULL UNIQUE,
+    created     TIMESTAMPTZ NOT NULL,
+
+    PRIMARY KEY(user_id),
+
+    CONSTRAINT fk_user
+        FOREIGN KEY(user_id)
+            REFERENCES users(id)
+            ON DELETE CASCADE
+);
@@ -1,5 +1,9 @@
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
 use chrono::Utc;
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use warp::http::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 
 use crate::Error;
@@ -43,3 +47,28 @@ pub fn jwt_from_headers(headers: &HeaderMap<HeaderValue>) -> Result<String, Erro
 
     Ok(auth.trim_start_matches(BEARER).to_string())
 }
+
+/// Generates a random token for public links, webhook secrets and calendar
+/// feeds encoded as 64 hex digits.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hashes `password` with argon2 and a random salt into a PHC string.
+pub fn hash_password(password: &str) -> Result<String, Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::PasswordHashError(e.to_string()))
+}
+
+/// Checks `password` against a hash created by [`hash_password`].
+pub fn verify_password(password: &str, hash: &str) -> Result<(), Error> {
+    let hash = PasswordHash::new(hash).map_err(|e| Error::PasswordHashError(e.to_string()))?;
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .map_err(|_| Error::InvalidPassword)
+}
@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::fs;
+
+use super::BlobStore;
+use crate::Error;
+
+pub const BLOB_DIR_ENV: &str = "NOTOR_BLOB_DIR";
+const DEFAULT_BLOB_DIR: &str = "./blobs";
+
+/// Keeps blobs as files in a local directory.
+pub struct FsBlobStore {
+    root: PathBuf,
+}
+
+impl FsBlobStore {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        FsBlobStore { root: root.into() }
+    }
+
+    /// Stores blobs in `NOTOR_BLOB_DIR`, `./blobs` by default.
+    pub fn from_env() -> Self {
+        Self::new(env::var(BLOB_DIR_ENV).unwrap_or_else(|_| DEFAULT_BLOB_DIR.to_string()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+fn blob_error(err: std::io::Error) -> Error {
+    Error::BlobError(err.to_string())
+}
+
+#[async_trait]
+impl BlobStore for FsBlobStore {
+    async fn put(&self, key: &str, data: Bytes, _: &str) -> Result<(), Error> {
+        let path = self.path(key);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).await.map_err(blob_error)?;
+        }
+
+        fs::write(path, data).await.map_err(blob_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, Error> {
+        fs::read(self.path(key))
+            .await
+            .map(Bytes::from)
+            .map_err(blob_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(key)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(blob_error(err)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+mod fs;
+mod s3;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::env;
+use std::sync::Arc;
+
+use crate::Error;
+pub use fs::FsBlobStore;
+pub use s3::S3BlobStore;
+
+/// Selects the blob store backend, either `fs` (default) or `s3`
+pub const BLOB_STORE_ENV: &str = "NOTOR_BLOB_STORE";
+
+/// Storage of attachment contents, addressed by keys like `user_id/attachment_id`.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), Error>;
+    async fn get(&self, key: &str) -> Result<Bytes, Error>;
+    /// Deleting a missing blob is not an error.
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+}
+
+pub type Blobs = Arc<dyn BlobStore>;
+
+/// Creates the blob store configured in the environment, see [`FsBlobStore::from_env`]
+/// and [`S3BlobStore::from_env`] for the settings of each backend.
+pub fn blob_store() -> Result<Blobs, Error> {
+    match env::var(BLOB_STORE_ENV).as_deref() {
+        Ok("fs") | Err(_) => Ok(Arc::new(FsBlobStore::from_env())),
+        Ok("s3") => Ok(Arc::new(S3BlobStore::from_env()?)),
+        Ok(other) => Err(Error::BlobError(format!("unknown blob store `{}`", other))),
+    }
+}
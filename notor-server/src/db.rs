@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 pub type DbConn = PgPool;
 pub type Db = Arc<PgPool>;
+pub type DbTx<'c> = sqlx::Transaction<'c, sqlx::Postgres>;
 
 const MAX_CONNECTIONS: u32 = 5;
 
@@ -0,0 +1,85 @@
+use futures::{stream, Stream};
+use sqlx::postgres::PgListener;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+
+use crate::db::{Db, DbConn};
+use crate::Error;
+use notor_core::models::Event;
+
+/// Channel the database announces changes on, see the `events` migration
+const CHANNEL: &str = "notor_events";
+/// Events buffered for each subscriber, slower ones skip the oldest
+const CAPACITY: usize = 256;
+/// Time to wait before listening again after the connection to the database failed
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Changes of notes and tags announced by the database. Every server instance listens
+/// for them so users receive changes made through any instance.
+#[derive(Clone)]
+pub struct Events(Sender<Event>);
+
+impl Events {
+    /// Starts listening for changes in the background.
+    pub fn listen(db: Db) -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        tokio::spawn(forward(db, tx.clone()));
+        Events(tx)
+    }
+
+    /// Receives changes of all users.
+    pub fn subscribe_all(&self) -> Receiver<Event> {
+        self.0.subscribe()
+    }
+
+    /// Stream of changes of notes and tags owned by the user.
+    pub fn subscribe(&self, user_id: i32) -> impl Stream<Item = Event> {
+        stream::unfold(self.0.subscribe(), move |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.user_id == user_id => return Some((event, rx)),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+/// Announces an event that isn't a change of the database, like a due reminder.
+pub async fn announce(event: &Event, db: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "SELECT pg_notify($1, $2)",
+        CHANNEL,
+        serde_json::to_string(event)?
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+async fn forward(db: Db, tx: Sender<Event>) {
+    loop {
+        if let Err(e) = listen(&db, &tx).await {
+            log::warn!("listening for events failed - {}", e);
+        }
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn listen(db: &Db, tx: &Sender<Event>) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(db).await?;
+    listener.listen(CHANNEL).await?;
+
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str(notification.payload()) {
+            // nobody might be subscribed
+            Ok(event) => {
+                let _ = tx.send(event);
+            }
+            Err(e) => log::warn!("invalid event `{}` - {}", notification.payload(), e),
+        }
+    }
+}
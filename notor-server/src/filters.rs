@@ -1,22 +1,105 @@
 #![allow(dead_code)]
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Deserialize)]
+use crate::Error;
+use notor_core::models::TagExpression;
+
+/// Filters of the note listing, also stored as the query of saved searches.
+#[derive(Default, Deserialize, Serialize)]
 pub struct QueryFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tag_id: Option<i32>,
+    /// Only notes filed directly in this notebook
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notebook_id: Option<i32>,
+    /// Full-text search terms matched against the title and content, supports quoted
+    /// phrases, `or` and `-term`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// Tag expression like `work/projects, -archive`, see [`TagExpression`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    /// Only notes created at or after this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only notes created before this time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favorite: Option<bool>,
+    /// Only archived notes when `true`, only active notes when `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    /// Lists archived notes along with active ones when `archived` isn't set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_archived: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<NoteSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
+    /// Lists pinned notes before the others regardless of `sort`, enabled by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pinned_first: Option<bool>,
+}
+
+/// Column by which listed notes are sorted
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    #[default]
+    Id,
+    Created,
+    /// Time of the last modification, notes that were never edited use their
+    /// creation time
+    Updated,
+    /// Username of the last editor
+    UpdatedBy,
+}
+
+impl AsRef<str> for NoteSort {
+    fn as_ref(&self) -> &str {
+        match self {
+            NoteSort::Id => "id",
+            NoteSort::Created => "created",
+            NoteSort::Updated => "updated",
+            NoteSort::UpdatedBy => "updated_by",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
 }
 
 impl QueryFilter {
     pub fn builder() -> QueryFilterBuilder {
         QueryFilterBuilder::default()
     }
+
+    pub fn tag_expression(&self) -> Result<TagExpression, Error> {
+        self.tags
+            .as_deref()
+            .map(str::parse)
+            .unwrap_or_else(|| Ok(TagExpression::default()))
+    }
 }
 
 #[derive(Default)]
 pub struct QueryFilterBuilder {
     pub limit: Option<i64>,
     pub tag_id: Option<i32>,
+    pub notebook_id: Option<i32>,
+    pub sort: Option<NoteSort>,
+    pub order: Option<SortOrder>,
 }
 
 impl QueryFilterBuilder {
@@ -31,10 +114,122 @@ impl QueryFilterBuilder {
         self
     }
 
+    pub fn notebook(mut self, n: i32) -> Self {
+        self.notebook_id = Some(n);
+        self
+    }
+
+    pub fn sort(mut self, sort: NoteSort, order: SortOrder) -> Self {
+        self.sort = Some(sort);
+        self.order = Some(order);
+        self
+    }
+
     pub fn build(self) -> QueryFilter {
         QueryFilter {
             limit: self.limit,
             tag_id: self.tag_id,
+            notebook_id: self.notebook_id,
+            sort: self.sort,
+            order: self.order,
+            ..Default::default()
         }
     }
 }
+
+/// Query parameters controlling how timestamps are presented in replies.
+#[derive(Default, Deserialize)]
+pub struct TimeQuery {
+    /// When set, timestamps are converted to the time zone configured in the
+    /// settings of the user instead of being returned in UTC.
+    pub local_time: Option<bool>,
+}
+
+impl TimeQuery {
+    pub fn is_local(&self) -> bool {
+        self.local_time.unwrap_or(false)
+    }
+}
+
+/// Query parameters of the task listing.
+#[derive(Default, Deserialize)]
+pub struct TaskQuery {
+    /// Only finished tasks when `true`, only open tasks when `false`
+    pub done: Option<bool>,
+}
+
+/// Query parameters of the upcoming reminders listing.
+#[derive(Default, Deserialize)]
+pub struct ReminderQuery {
+    /// Only reminders and due dates before this time
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+/// Query parameters of note updates.
+#[derive(Default, Deserialize)]
+pub struct LinkQuery {
+    /// When a note is renamed, rewrites `[[old title]]` links in other notes of the
+    /// user to the new title.
+    pub rewrite_links: Option<bool>,
+}
+
+impl LinkQuery {
+    pub fn rewrite(&self) -> bool {
+        self.rewrite_links.unwrap_or(false)
+    }
+}
+
+/// Neighbourhoods deeper than this are cut off
+pub const GRAPH_DEPTH_MAX: usize = 5;
+
+/// Query parameters of the note graph.
+#[derive(Default, Deserialize)]
+pub struct GraphQuery {
+    /// Limits the graph to the neighbourhood of this note
+    pub note_id: Option<i32>,
+    /// Number of edges between the note and the farthest node of its neighbourhood,
+    /// `1` by default and at most [`GRAPH_DEPTH_MAX`]
+    pub depth: Option<usize>,
+    pub include_archived: Option<bool>,
+}
+
+impl GraphQuery {
+    pub fn depth(&self) -> usize {
+        self.depth.unwrap_or(1).min(GRAPH_DEPTH_MAX)
+    }
+}
+
+/// Query parameters of the tag listing.
+#[derive(Default, Deserialize)]
+pub struct TagQuery {
+    pub limit: Option<i64>,
+    /// When set, tags are returned as a tree of nested tags instead of a flat list.
+    pub tree: Option<bool>,
+    pub sort: Option<TagSort>,
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagSort {
+    #[default]
+    Name,
+    /// Number of notes the tag is attached to directly
+    Usage,
+}
+
+impl AsRef<str> for TagSort {
+    fn as_ref(&self) -> &str {
+        match self {
+            TagSort::Name => "name",
+            TagSort::Usage => "usage",
+        }
+    }
+}
+
+impl TagQuery {
+    pub fn is_tree(&self) -> bool {
+        self.tree.unwrap_or(false)
+    }
+}
@@ -0,0 +1,182 @@
+use bytes::{Buf, Bytes};
+use futures::{StreamExt, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use warp::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS};
+use warp::http::Response;
+use warp::multipart::{FormData, Part};
+use warp::{reject, reply, Rejection, Reply};
+
+use super::notes::{authorized_note, Access};
+use crate::blobs::Blobs;
+use crate::db::Db;
+use crate::models::{
+    delete_attachment as _delete_attachment, load_attachment, note_attachments, save_attachment,
+};
+use crate::Error;
+use notor_core::models::{sniff_content_type, Attachment};
+
+/// Name of the multipart field carrying the uploaded files
+const FILE_FIELD: &str = "file";
+const FILENAME_MAX_LEN: usize = 256;
+/// Characters allowed unescaped in the `filename*` parameter (RFC 5987 `attr-char`)
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// Loads the attachment making sure that the user can `access` its note.
+async fn authorized_attachment(
+    id: i32,
+    username: &str,
+    access: Access,
+    conn: &Db,
+) -> Result<Attachment, Rejection> {
+    let attachment = load_attachment(id, conn).await.map_err(reject::custom)?;
+    authorized_note(attachment.note_id, username, access, conn).await?;
+
+    Ok(attachment)
+}
+
+/// Strips directories some browsers send along with the name of the file.
+fn attachment_filename(filename: Option<&str>) -> Result<String, Error> {
+    let filename = filename
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(str::trim)
+        .unwrap_or_default();
+
+    if filename.is_empty() {
+        Err(Error::InvalidAttachment("file name is missing".into()))
+    } else if filename.chars().count() > FILENAME_MAX_LEN {
+        Err(Error::InvalidAttachment(format!(
+            "file name is longer than {} characters",
+            FILENAME_MAX_LEN
+        )))
+    } else {
+        Ok(filename.to_string())
+    }
+}
+
+async fn part_data(part: Part) -> Result<Bytes, Error> {
+    part.stream()
+        .map_err(|e| Error::InvalidAttachment(e.to_string()))
+        .try_fold(Vec::new(), |mut data, buf| async move {
+            data.extend_from_slice(buf.chunk());
+            Ok(data)
+        })
+        .await
+        .map(Bytes::from)
+}
+
+pub(crate) async fn post_attachments(
+    note_id: i32,
+    mut form: FormData,
+    username: String,
+    conn: Db,
+    blobs: Blobs,
+) -> Result<impl Reply, Rejection> {
+    let note = authorized_note(note_id, &username, Access::Write, &conn).await?;
+
+    let mut attachments = vec![];
+    while let Some(part) = form.next().await {
+        let part = part.map_err(|e| reject::custom(Error::InvalidAttachment(e.to_string())))?;
+        if part.name() != FILE_FIELD {
+            continue;
+        }
+
+        let filename = attachment_filename(part.filename()).map_err(reject::custom)?;
+        let data = part_data(part).await.map_err(reject::custom)?;
+        if data.is_empty() {
+            return Err(reject::custom(Error::InvalidAttachment(format!(
+                "`{}` is empty",
+                filename
+            ))));
+        }
+        let content_type = sniff_content_type(&data);
+
+        attachments.push(
+            save_attachment(&note, &filename, content_type, data, &blobs, &conn)
+                .await
+                .map_err(reject::custom)?,
+        );
+    }
+
+    if attachments.is_empty() {
+        return Err(reject::custom(Error::InvalidAttachment(format!(
+            "no `{}` field was uploaded",
+            FILE_FIELD
+        ))));
+    }
+
+    Ok(reply::json(&attachments))
+}
+
+pub(crate) async fn get_note_attachments(
+    note_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Read, &conn).await?;
+
+    note_attachments(note_id, &conn)
+        .await
+        .map(|attachments| reply::json(&attachments))
+        .map_err(reject::custom)
+}
+
+/// Sends the content of the attachment, images are displayed inline while other files
+/// are downloaded.
+pub(crate) async fn get_attachment(
+    id: i32,
+    username: String,
+    conn: Db,
+    blobs: Blobs,
+) -> Result<impl Reply, Rejection> {
+    let attachment = authorized_attachment(id, &username, Access::Read, &conn).await?;
+    let data = blobs
+        .get(&attachment.blob_key())
+        .await
+        .map_err(reject::custom)?;
+
+    let disposition = if attachment.is_image() {
+        "inline"
+    } else {
+        "attachment"
+    };
+
+    Response::builder()
+        .header(CONTENT_TYPE, &attachment.content_type)
+        .header(X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(
+            CONTENT_DISPOSITION,
+            format!(
+                "{}; filename*=UTF-8''{}",
+                disposition,
+                utf8_percent_encode(&attachment.filename, ATTR_CHAR)
+            ),
+        )
+        .body(data)
+        .map_err(|e| reject::custom(Error::BlobError(e.to_string())))
+}
+
+pub(crate) async fn delete_attachment(
+    id: i32,
+    username: String,
+    conn: Db,
+    blobs: Blobs,
+) -> Result<impl Reply, Rejection> {
+    let attachment = authorized_attachment(id, &username, Access::Write, &conn).await?;
+
+    _delete_attachment(&attachment, &blobs, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
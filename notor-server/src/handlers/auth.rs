@@ -31,7 +31,7 @@ pub async fn authorize_token(
     )
     .map_err(Error::from)?;
 
-    if role != decoded.claims.role.parse()? {}
+    let token_role: UserRole = decoded.claims.role.parse()?;
 
     let claim = load_claims(&decoded.claims.sub, &db).await?;
 
@@ -45,6 +45,10 @@ pub async fn authorize_token(
         return Err(reject::custom(Error::AuthTokenExpired));
     }
 
+    if !token_role.allows(&role) {
+        return Err(reject::custom(Error::UnauthorizedAccess));
+    }
+
     Ok(decoded.claims.sub)
 }
 
@@ -55,7 +59,7 @@ pub(crate) async fn handle_login(auth: JsonAuth, conn: Db) -> Result<impl Reply,
         return Err(reject::custom(Error::InvalidPassword));
     }
 
-    if let Some(_) = load_claims_if_exists(&user.username, &conn).await {
+    if load_claims_if_exists(&user.username, &conn).await.is_some() {
         delete_claims(&user.username, &conn).await?;
     }
 
@@ -0,0 +1,68 @@
+use chrono::Utc;
+use std::env;
+use warp::http::header::CONTENT_TYPE;
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::auth::random_token;
+use crate::db::Db;
+use crate::models::{
+    calendar_entries, calendar_feed, delete_calendar_feed as _delete_calendar_feed,
+    find_calendar_feed, save_calendar_feed,
+};
+use notor_core::calendar;
+
+/// Address of notor-web that entries of calendar feeds link to
+pub const WEB_URL_ENV: &str = "NOTOR_WEB_URL";
+const DEFAULT_WEB_URL: &str = "http://localhost:3694";
+const FEED_EXTENSION: &str = ".ics";
+const CALENDAR_NAME: &str = "notor";
+
+pub(crate) async fn get_calendar_feed(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    calendar_feed(username, &conn)
+        .await
+        .map(|feed| reply::json(&feed))
+        .map_err(reject::custom)
+}
+
+/// Creates the calendar feed of the user or gives it a new address.
+pub(crate) async fn put_calendar_feed(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    save_calendar_feed(username, &random_token(), &conn)
+        .await
+        .map(|feed| reply::json(&feed))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn delete_calendar_feed(
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    _delete_calendar_feed(username, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+/// Serves the feed named like `<token>.ics` as an iCalendar file.
+pub(crate) async fn get_calendar(file: String, conn: Db) -> Result<impl Reply, Rejection> {
+    let token = file
+        .strip_suffix(FEED_EXTENSION)
+        .ok_or_else(warp::reject::not_found)?;
+    let feed = find_calendar_feed(token, &conn)
+        .await
+        .map_err(reject::custom)?;
+    let entries = calendar_entries(feed.user_id, &conn)
+        .await
+        .map_err(reject::custom)?;
+
+    let web_url = env::var(WEB_URL_ENV).unwrap_or_else(|_| DEFAULT_WEB_URL.to_string());
+    let web_url = web_url.trim_end_matches('/');
+    let body = calendar(CALENDAR_NAME, &entries, &Utc::now(), |id| {
+        format!("{}/web/notes/{}", web_url, id)
+    });
+
+    Ok(reply::with_header(
+        body,
+        CONTENT_TYPE,
+        "text/calendar; charset=utf-8",
+    ))
+}
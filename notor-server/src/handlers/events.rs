@@ -0,0 +1,22 @@
+use futures::StreamExt;
+use warp::sse::{self, Event as SseEvent};
+use warp::{reject, Rejection, Reply};
+
+use crate::db::Db;
+use crate::events::Events;
+use crate::models::load_user;
+
+/// Streams changes of notes and tags of the user as server-sent events.
+pub(crate) async fn get_events(
+    username: String,
+    conn: Db,
+    events: Events,
+) -> Result<impl Reply, Rejection> {
+    let user = load_user(&username, &conn).await.map_err(reject::custom)?;
+
+    let stream = events
+        .subscribe(user.id)
+        .map(|event| SseEvent::default().event("change").json_data(event));
+
+    Ok(sse::reply(sse::keep_alive().stream(stream)))
+}
@@ -0,0 +1,25 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use super::notes::{authorized_note, Access};
+use crate::db::Db;
+use crate::filters::GraphQuery;
+use crate::models::user_graph;
+
+pub(crate) async fn get_graph(
+    query: GraphQuery,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    if let Some(note_id) = query.note_id {
+        authorized_note(note_id, &username, Access::Owner, &conn).await?;
+    }
+
+    let graph = user_graph(&username, query.include_archived.unwrap_or(false), &conn)
+        .await
+        .map_err(reject::custom)?;
+
+    match query.note_id {
+        Some(note_id) => Ok(reply::json(&graph.neighbourhood(note_id, query.depth()))),
+        None => Ok(reply::json(&graph)),
+    }
+}
@@ -0,0 +1,23 @@
+use warp::ws::Ws;
+use warp::{Rejection, Reply};
+
+use super::notes::{authorized_note, Access};
+use crate::db::Db;
+use crate::live::Sessions;
+
+/// Opens the live editing session of the note, users who can only read the note receive
+/// edits of others without being able to make their own.
+pub(crate) async fn note_live(
+    id: i32,
+    ws: Ws,
+    username: String,
+    conn: Db,
+    sessions: Sessions,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Read, &conn).await?;
+    let editable = authorized_note(id, &username, Access::Write, &conn)
+        .await
+        .is_ok();
+
+    Ok(ws.on_upgrade(move |socket| sessions.connect(socket, id, username, editable, conn)))
+}
@@ -1,10 +1,23 @@
+pub mod attachments;
 pub mod auth;
+pub mod calendar;
+pub mod events;
+pub mod graph;
+pub mod live;
+pub mod notebooks;
 pub mod notes;
+pub mod public_links;
+pub mod reminders;
+pub mod saved_searches;
+pub mod settings;
+pub mod shares;
 pub mod tags;
+pub mod tasks;
+pub mod webhooks;
 
 use crate::db::Db;
 use warp::Rejection;
 
-fn lock_db<'d>(db: &'d Db) -> Result<&'d Db, Rejection> {
+fn lock_db(db: &Db) -> Result<&Db, Rejection> {
     Ok(db)
 }
@@ -0,0 +1,107 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::db::Db;
+use crate::models::{
+    delete_notebook as _delete_notebook, load_notebook, load_user_from_id, save_notebook,
+    update_notebook as _update_notebook, user_notebooks,
+};
+use crate::Error;
+use notor_core::models::{NewNotebook, Notebook};
+
+pub(crate) async fn get_notebooks(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    user_notebooks(username, &conn)
+        .await
+        .map(|notebooks| reply::json(&notebooks))
+        .map_err(reject::custom)
+}
+
+/// Loads the notebook making sure that it belongs to the user.
+pub(super) async fn authorized_notebook(
+    id: i32,
+    username: &str,
+    conn: &Db,
+) -> Result<Notebook, Rejection> {
+    let notebook = load_notebook(id, conn).await.map_err(reject::custom)?;
+
+    let user = load_user_from_id(notebook.user_id, conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if user.username != username {
+        Err(reject::custom(Error::UnauthorizedAccess))
+    } else {
+        Ok(notebook)
+    }
+}
+
+/// Makes sure that a notebook used as a parent or as the target of a note exists and
+/// belongs to the user.
+pub(super) async fn check_notebook(
+    id: Option<i32>,
+    username: &str,
+    conn: &Db,
+) -> Result<(), Rejection> {
+    match id {
+        Some(id) => authorized_notebook(id, username, conn)
+            .await
+            .map(|_| ())
+            .map_err(|_| {
+                reject::custom(Error::InvalidNotebook(format!(
+                    "notebook `{}` doesn't exist",
+                    id
+                )))
+            }),
+        None => Ok(()),
+    }
+}
+
+pub(crate) async fn get_notebook(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_notebook(id, &username, &conn)
+        .await
+        .map(|notebook| reply::json(&notebook))
+}
+
+pub(crate) async fn put_notebook(
+    notebook: NewNotebook,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    check_notebook(notebook.parent_id, &username, &conn).await?;
+
+    save_notebook(&notebook, &username, &conn)
+        .await
+        .map(|notebook| reply::json(&notebook))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn update_notebook(
+    id: i32,
+    notebook: NewNotebook,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_notebook(id, &username, &conn).await?;
+    check_notebook(notebook.parent_id, &username, &conn).await?;
+
+    _update_notebook(id, &notebook, &conn)
+        .await
+        .map(|notebook| reply::json(&notebook))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn delete_notebook(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_notebook(id, &username, &conn).await?;
+
+    _delete_notebook(id, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
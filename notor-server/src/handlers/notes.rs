@@ -1,50 +1,131 @@
+use chrono_tz::Tz;
+use percent_encoding::percent_decode_str;
 use warp::{reject, reply, Rejection, Reply};
 
 use super::lock_db;
+use super::notebooks::check_notebook;
+use crate::blobs::Blobs;
 use crate::db::Db;
-use crate::filters::QueryFilter;
+use crate::filters::{LinkQuery, QueryFilter, TimeQuery};
+use crate::live::Sessions;
 use crate::models::{
-    delete_note as del_note, load_note, load_notes, load_user_from_id, note_tags, save_note,
-    save_tag, search_tag, tag_note as _tag_note, untag_note as _untag_note,
-    update_note as upd_note,
+    archive_note as _archive_note, delete_note as del_note, favorite_note as _favorite_note,
+    find_or_create_tag, load_note, load_note_task, load_notes, load_user_from_id,
+    load_user_settings, move_note as _move_note, note_backlinks, note_links, note_permission,
+    note_revisions, note_tags, note_tasks, patch_note as _patch_note, pin_note as _pin_note,
+    save_note, snooze_reminder, tag_note as _tag_note, toggle_note_task as _toggle_note_task,
+    untag_note as _untag_note, update_note as upd_note,
 };
 use crate::Error;
-use notor_core::models::{NewNote, NewTag};
+use notor_core::models::{normalize_tag_path, NewNote, Note, ReminderSnooze, SharePermission};
+use notor_core::{toggle_task, NotePatch};
 
 pub(crate) async fn get_notes(
     filter: QueryFilter,
+    time: TimeQuery,
     username: String,
     conn: Db,
 ) -> Result<impl Reply, Rejection> {
-    load_notes(filter, username, &conn)
+    let notes = load_notes(filter, &username, &conn)
         .await
-        .map(|note| reply::json(&note))
-        .map_err(reject::custom)
+        .map_err(reject::custom)?;
+
+    if time.is_local() {
+        let tz = user_timezone(&username, &conn).await?;
+        let notes: Vec<_> = notes.into_iter().map(|note| note.localize(&tz)).collect();
+        Ok(reply::json(&notes))
+    } else {
+        Ok(reply::json(&notes))
+    }
 }
 
-pub(crate) async fn get_note(id: i32, username: String, conn: Db) -> Result<impl Reply, Rejection> {
-    let note = load_note(id, &conn).await.map_err(reject::custom)?;
+/// What a handler is about to do with a note.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Access {
+    /// Reading the note with its tags, links and attachments
+    Read,
+    /// Changing the title and content or the attachments
+    Write,
+    /// Organizing, sharing or deleting the note
+    Owner,
+}
 
-    let user = load_user_from_id(note.user_id, &conn)
+/// Loads the note making sure that it belongs to the user or that the owner shared it
+/// with them with a permission allowing `access`.
+pub(super) async fn authorized_note(
+    id: i32,
+    username: &str,
+    access: Access,
+    conn: &Db,
+) -> Result<Note, Rejection> {
+    let note = load_note(id, conn).await.map_err(reject::custom)?;
+
+    let user = load_user_from_id(note.user_id, conn)
         .await
         .map_err(reject::custom)?;
+    if user.username == username {
+        return Ok(note);
+    }
 
-    if user.username != username {
-        Err(reject::custom(Error::UnauthorizedAccess))
+    let permission = match access {
+        Access::Owner => None,
+        Access::Read | Access::Write => note_permission(id, username, conn)
+            .await
+            .map_err(reject::custom)?,
+    };
+
+    match (access, permission) {
+        (Access::Read, Some(_)) | (Access::Write, Some(SharePermission::Editor)) => Ok(note),
+        _ => Err(reject::custom(Error::UnauthorizedAccess)),
+    }
+}
+
+pub(crate) async fn get_note(
+    id: i32,
+    time: TimeQuery,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let note = authorized_note(id, &username, Access::Read, &conn).await?;
+
+    if time.is_local() {
+        let tz = user_timezone(&username, &conn).await?;
+        Ok(reply::json(&note.localize(&tz)))
     } else {
         Ok(reply::json(&note))
     }
 }
 
-pub(crate) async fn put_note(note: NewNote, _: String, conn: Db) -> Result<impl Reply, Rejection> {
-    save_note(&note, &conn)
+pub(super) async fn user_timezone(username: &str, conn: &Db) -> Result<Tz, Rejection> {
+    load_user_settings(username, conn)
+        .await
+        .map(|settings| settings.tz())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn put_note(
+    note: NewNote,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    note.check_owner(&username).map_err(reject::custom)?;
+    check_notebook(note.notebook_id, &username, &conn).await?;
+
+    save_note(&note, &username, &conn)
         .await
         .map(|note| reply::json(&note))
         .map_err(reject::custom)
 }
 
-pub(crate) async fn delete_note(id: i32, _: String, conn: Db) -> Result<impl Reply, Rejection> {
-    del_note(id, &conn)
+pub(crate) async fn delete_note(
+    id: i32,
+    username: String,
+    conn: Db,
+    blobs: Blobs,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Owner, &conn).await?;
+
+    del_note(id, &blobs, &conn)
         .await
         .map(|_| reply::reply())
         .map_err(reject::custom)
@@ -53,13 +134,74 @@ pub(crate) async fn delete_note(id: i32, _: String, conn: Db) -> Result<impl Rep
 pub(crate) async fn update_note(
     id: i32,
     note: NewNote,
-    _: String,
+    links: LinkQuery,
+    username: String,
     conn: Db,
+    sessions: Sessions,
 ) -> Result<impl Reply, Rejection> {
-    upd_note(id, &note, &conn)
+    note.check_owner(&username).map_err(reject::custom)?;
+    // rewriting links changes other notes of the owner
+    let access = if links.rewrite() {
+        Access::Owner
+    } else {
+        Access::Write
+    };
+    authorized_note(id, &username, access, &conn).await?;
+
+    let write = |_| upd_note(id, &note, links.rewrite(), &username, &conn);
+    match sessions
+        .write(id, &username, |_| Ok(note.content.clone()), write)
         .await
-        .map(|_| reply::reply())
-        .map_err(reject::custom)
+    {
+        Some(result) => result,
+        None => upd_note(id, &note, links.rewrite(), &username, &conn).await,
+    }
+    .map(|_| reply::reply())
+    .map_err(reject::custom)
+}
+
+pub(crate) async fn patch_note(
+    id: i32,
+    patch: NotePatch,
+    links: LinkQuery,
+    username: String,
+    conn: Db,
+    sessions: Sessions,
+) -> Result<impl Reply, Rejection> {
+    patch.validate().map_err(reject::custom)?;
+    let access = if patch.organizes() || links.rewrite() {
+        Access::Owner
+    } else {
+        Access::Write
+    };
+    authorized_note(id, &username, access, &conn).await?;
+    if let Some(notebook_id) = patch.notebook_id {
+        check_notebook(notebook_id, &username, &conn).await?;
+    }
+
+    let written = if patch.content.is_some() || !patch.content_patch.is_empty() {
+        let (rewrite_links, editor, db) = (links.rewrite(), username.as_str(), &conn);
+        // line patches apply to the live content which may be ahead of the saved one
+        let patch_live = |content: &str| patch.patched_content(Some(content.to_string()));
+        let write = |content| {
+            let patch = NotePatch {
+                content: Some(content),
+                content_patch: vec![],
+                ..patch.clone()
+            };
+            async move { _patch_note(id, &patch, rewrite_links, editor, db).await }
+        };
+        sessions.write(id, &username, patch_live, write).await
+    } else {
+        None
+    };
+
+    match written {
+        Some(result) => result,
+        None => _patch_note(id, &patch, links.rewrite(), &username, &conn).await,
+    }
+    .map(|note| reply::json(&note))
+    .map_err(reject::custom)
 }
 
 pub(crate) async fn tag_note(
@@ -68,24 +210,17 @@ pub(crate) async fn tag_note(
     username: String,
     conn: Db,
 ) -> Result<impl Reply, Rejection> {
-    let tag_id_ = match search_tag(&tag, &username, &conn)
+    // nested tags arrive percent encoded as `work%2Fprojects`
+    let tag = percent_decode_str(&tag)
+        .decode_utf8()
+        .map_err(|e| reject::custom(Error::InvalidTag(e.to_string())))?;
+    let tag = normalize_tag_path(&tag).map_err(reject::custom)?;
+    authorized_note(note_id_, &username, Access::Owner, &conn).await?;
+    let tag_id_ = find_or_create_tag(&tag, &username, &conn)
         .await
-        .map_err(reject::custom)?
-    {
-        Some(id) => Ok(id),
-        None => save_tag(
-            &NewTag {
-                name: tag,
-                username,
-            },
-            &conn,
-        )
-        .await
-        .map(|tag| tag.id),
-    }
-    .map_err(reject::custom)?;
+        .map_err(reject::custom)?;
 
-    _tag_note(note_id_, tag_id_, &conn)
+    _tag_note(note_id_, tag_id_, &username, &conn)
         .await
         .map(|_| reply::reply())
         .map_err(reject::custom)
@@ -94,23 +229,235 @@ pub(crate) async fn tag_note(
 pub(crate) async fn untag_note(
     note_id_: i32,
     tag_id_: i32,
-    _: String,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id_, &username, Access::Owner, &conn).await?;
+
+    _untag_note(note_id_, tag_id_, &username, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn move_note(
+    note_id: i32,
+    notebook_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+    check_notebook(Some(notebook_id), &username, &conn).await?;
+
+    _move_note(note_id, Some(notebook_id), &username, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn unfile_note(
+    note_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+
+    _move_note(note_id, None, &username, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn pin_note(id: i32, username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    set_pinned(id, true, username, conn).await
+}
+
+pub(crate) async fn unpin_note(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    set_pinned(id, false, username, conn).await
+}
+
+async fn set_pinned(
+    id: i32,
+    pinned: bool,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Owner, &conn).await?;
+
+    _pin_note(id, pinned, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn favorite_note(
+    id: i32,
+    username: String,
     conn: Db,
 ) -> Result<impl Reply, Rejection> {
-    _untag_note(note_id_, tag_id_, &conn)
+    set_favorite(id, true, username, conn).await
+}
+
+pub(crate) async fn unfavorite_note(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    set_favorite(id, false, username, conn).await
+}
+
+async fn set_favorite(
+    id: i32,
+    favorite: bool,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Owner, &conn).await?;
+
+    _favorite_note(id, favorite, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn archive_note(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    set_archived(id, true, username, conn).await
+}
+
+pub(crate) async fn unarchive_note(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    set_archived(id, false, username, conn).await
+}
+
+async fn set_archived(
+    id: i32,
+    archived: bool,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Owner, &conn).await?;
+
+    _archive_note(id, archived, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+/// Postpones the reminder of the note, this also sets a reminder on notes without one.
+pub(crate) async fn snooze_note_reminder(
+    id: i32,
+    snooze: ReminderSnooze,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Owner, &conn).await?;
+    let remind_at = snooze
+        .remind_at(chrono::offset::Utc::now())
+        .map_err(reject::custom)?;
+
+    snooze_reminder(id, remind_at, &conn)
         .await
         .map(|_| reply::reply())
         .map_err(reject::custom)
 }
 
+pub(crate) async fn get_note_backlinks(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Read, &conn).await?;
+
+    note_backlinks(id, &username, &conn)
+        .await
+        .map(|notes| reply::json(&notes))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_note_links(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Read, &conn).await?;
+
+    note_links(id, &username, &conn)
+        .await
+        .map(|notes| reply::json(&notes))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_note_revisions(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Read, &conn).await?;
+
+    note_revisions(id, &conn)
+        .await
+        .map(|revisions| reply::json(&revisions))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_note_tasks(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Read, &conn).await?;
+
+    note_tasks(id, &conn)
+        .await
+        .map(|tasks| reply::json(&tasks))
+        .map_err(reject::custom)
+}
+
+/// Checks or unchecks the task at `position`, this edits the content of the note.
+pub(crate) async fn toggle_note_task(
+    id: i32,
+    position: usize,
+    username: String,
+    conn: Db,
+    sessions: Sessions,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(id, &username, Access::Write, &conn).await?;
+
+    let toggle = |content: &str| toggle_task(content, position);
+    match sessions.rewrite(id, &username, toggle, &conn).await {
+        Some(true) => {}
+        Some(false) => return Err(reject::custom(Error::from(sqlx::Error::RowNotFound))),
+        None => _toggle_note_task(id, position, &username, &conn)
+            .await
+            .map_err(reject::custom)?,
+    }
+
+    load_note_task(id, position as i32, &conn)
+        .await
+        .map(|task| reply::json(&task))
+        .map_err(reject::custom)
+}
+
 pub(crate) async fn get_note_tags(
     note_id_: i32,
-    _: String,
+    username: String,
     conn: Db,
 ) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id_, &username, Access::Read, &conn).await?;
     let conn = lock_db(&conn)?;
 
-    note_tags(note_id_, &conn)
+    note_tags(note_id_, conn)
         .await
         .map(|tags| reply::json(&tags))
         .map_err(reject::custom)
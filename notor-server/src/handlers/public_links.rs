@@ -0,0 +1,105 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use super::notes::{authorized_note, Access};
+use crate::auth::{hash_password, random_token, verify_password};
+use crate::db::Db;
+use crate::models::{
+    delete_public_link as _delete_public_link, find_public_link, load_public_link, public_links,
+    save_public_link, view_public_link,
+};
+use crate::Error;
+use notor_core::models::{LinkPassword, NewPublicLink};
+
+pub(crate) async fn get_public_links(
+    note_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+
+    public_links(note_id, &conn)
+        .await
+        .map(|links| reply::json(&links))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn put_public_link(
+    note_id: i32,
+    link: NewPublicLink,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    link.validate().map_err(reject::custom)?;
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+
+    let password_hash = match link.password {
+        Some(password) => Some(
+            tokio::task::spawn_blocking(move || hash_password(&password))
+                .await
+                .map_err(|e| reject::custom(Error::PasswordHashError(e.to_string())))?
+                .map_err(reject::custom)?,
+        ),
+        None => None,
+    };
+
+    save_public_link(
+        note_id,
+        &random_token(),
+        link.expires,
+        password_hash.as_deref(),
+        &conn,
+    )
+    .await
+    .map(|link| reply::json(&link))
+    .map_err(reject::custom)
+}
+
+pub(crate) async fn delete_public_link(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let link = load_public_link(id, &conn).await.map_err(reject::custom)?;
+    authorized_note(link.note_id, &username, Access::Owner, &conn).await?;
+
+    _delete_public_link(id, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+/// Shows the note of a link that isn't protected by a password.
+pub(crate) async fn get_public_note(token: String, conn: Db) -> Result<impl Reply, Rejection> {
+    public_note(token, None, conn).await
+}
+
+pub(crate) async fn post_public_note(
+    token: String,
+    password: LinkPassword,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    public_note(token, Some(password.password), conn).await
+}
+
+async fn public_note(
+    token: String,
+    password: Option<String>,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let (link_id, hash) = find_public_link(&token, &conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if let Some(hash) = hash {
+        let password = password.ok_or_else(|| reject::custom(Error::InvalidPassword))?;
+        tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+            .await
+            .map_err(|e| reject::custom(Error::PasswordHashError(e.to_string())))?
+            .map_err(reject::custom)?;
+    }
+
+    view_public_link(link_id, &conn)
+        .await
+        .map(|note| reply::json(&note))
+        .map_err(reject::custom)
+}
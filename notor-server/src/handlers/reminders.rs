@@ -0,0 +1,17 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::db::Db;
+use crate::filters::ReminderQuery;
+use crate::models::upcoming_reminders;
+
+/// Lists pending reminders and upcoming due dates of active notes of the user.
+pub(crate) async fn get_upcoming_reminders(
+    query: ReminderQuery,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    upcoming_reminders(username, query.before, query.limit, &conn)
+        .await
+        .map(|reminders| reply::json(&reminders))
+        .map_err(reject::custom)
+}
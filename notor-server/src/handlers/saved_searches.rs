@@ -0,0 +1,126 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use super::notes::user_timezone;
+use crate::db::Db;
+use crate::filters::{QueryFilter, TimeQuery};
+use crate::models::{
+    delete_saved_search as _delete_saved_search, load_notes, load_saved_search, load_user_from_id,
+    save_saved_search, update_saved_search as _update_saved_search, user_saved_searches,
+};
+use crate::Error;
+use notor_core::models::{NewSavedSearch, SavedSearch};
+
+/// Parses the stored query of a saved search.
+fn search_filter(query: &serde_json::Value) -> Result<QueryFilter, Error> {
+    let filter: QueryFilter =
+        serde_json::from_value(query.clone()).map_err(|e| Error::InvalidSearch(e.to_string()))?;
+    filter.tag_expression()?;
+
+    Ok(filter)
+}
+
+/// Validates the query of a new search and returns it in its normalized form.
+fn normalized_query(search: &NewSavedSearch) -> Result<serde_json::Value, Rejection> {
+    search_filter(&search.query)
+        .and_then(|filter| serde_json::to_value(&filter).map_err(Error::from))
+        .map_err(reject::custom)
+}
+
+/// Loads the saved search making sure that it belongs to the user.
+async fn authorized_search(id: i32, username: &str, conn: &Db) -> Result<SavedSearch, Rejection> {
+    let search = load_saved_search(id, conn).await.map_err(reject::custom)?;
+
+    let user = load_user_from_id(search.user_id, conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if user.username != username {
+        Err(reject::custom(Error::UnauthorizedAccess))
+    } else {
+        Ok(search)
+    }
+}
+
+pub(crate) async fn get_saved_searches(
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    user_saved_searches(username, &conn)
+        .await
+        .map(|searches| reply::json(&searches))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_saved_search(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_search(id, &username, &conn)
+        .await
+        .map(|search| reply::json(&search))
+}
+
+/// Evaluates the saved search the same way as the `/notes` listing.
+pub(crate) async fn get_saved_search_notes(
+    id: i32,
+    time: TimeQuery,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let search = authorized_search(id, &username, &conn).await?;
+    let filter = search_filter(&search.query).map_err(reject::custom)?;
+
+    let notes = load_notes(filter, &username, &conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if time.is_local() {
+        let tz = user_timezone(&username, &conn).await?;
+        let notes: Vec<_> = notes.into_iter().map(|note| note.localize(&tz)).collect();
+        Ok(reply::json(&notes))
+    } else {
+        Ok(reply::json(&notes))
+    }
+}
+
+pub(crate) async fn put_saved_search(
+    search: NewSavedSearch,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let query = normalized_query(&search)?;
+
+    save_saved_search(&search.name, &query, &username, &conn)
+        .await
+        .map(|search| reply::json(&search))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn update_saved_search(
+    id: i32,
+    search: NewSavedSearch,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let query = normalized_query(&search)?;
+    authorized_search(id, &username, &conn).await?;
+
+    _update_saved_search(id, &search.name, &query, &conn)
+        .await
+        .map(|search| reply::json(&search))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn delete_saved_search(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_search(id, &username, &conn).await?;
+
+    _delete_saved_search(id, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
@@ -0,0 +1,25 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::db::Db;
+use crate::models::{load_user_settings, save_user_settings};
+use notor_core::models::NewUserSettings;
+
+pub(crate) async fn get_settings(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    load_user_settings(username, &conn)
+        .await
+        .map(|settings| reply::json(&settings))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn update_settings(
+    settings: NewUserSettings,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    settings.validate().map_err(reject::custom)?;
+
+    save_user_settings(username, &settings, &conn)
+        .await
+        .map(|settings| reply::json(&settings))
+        .map_err(reject::custom)
+}
@@ -0,0 +1,75 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use super::notes::{authorized_note, Access};
+use crate::db::Db;
+use crate::models::{
+    load_note, load_user_from_id, note_shares, share_note, shared_notes, unshare_note,
+};
+use crate::Error;
+use notor_core::models::NewNoteShare;
+
+pub(crate) async fn get_shared_notes(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    shared_notes(&username, &conn)
+        .await
+        .map(|notes| reply::json(&notes))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_note_shares(
+    note_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+
+    note_shares(note_id, &conn)
+        .await
+        .map(|shares| reply::json(&shares))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn put_note_share(
+    note_id: i32,
+    share: NewNoteShare,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_note(note_id, &username, Access::Owner, &conn).await?;
+    if share.username == username {
+        return Err(reject::custom(Error::InvalidShare(
+            "a note can't be shared with its owner".into(),
+        )));
+    }
+
+    share_note(note_id, &share, &conn)
+        .await
+        .map(|share| reply::json(&share))
+        .map_err(reject::custom)
+}
+
+/// Stops sharing the note with the user `user_id`. Besides the owner, users can remove
+/// notes shared with them.
+pub(crate) async fn delete_note_share(
+    note_id: i32,
+    user_id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    let note = load_note(note_id, &conn).await.map_err(reject::custom)?;
+    let owner = load_user_from_id(note.user_id, &conn)
+        .await
+        .map_err(reject::custom)?;
+    if owner.username != username {
+        let user = load_user_from_id(user_id, &conn)
+            .await
+            .map_err(reject::custom)?;
+        if user.username != username {
+            return Err(reject::custom(Error::UnauthorizedAccess));
+        }
+    }
+
+    unshare_note(note_id, user_id, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
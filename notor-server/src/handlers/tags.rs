@@ -1,36 +1,142 @@
 use warp::{reject, reply, Rejection, Reply};
 
 use crate::db::Db;
-use crate::filters::QueryFilter;
-use crate::models::{delete_tag as _delete_tag, load_tag, save_tag, user_tags};
-use notor_core::models::NewTag;
+use crate::filters::TagQuery;
+use crate::models::{
+    delete_tag as _delete_tag, find_or_create_tag, load_tag, load_user_from_id, merge_tags,
+    update_tag as _update_tag, user_tags,
+};
+use crate::Error;
+use notor_core::models::{normalize_tag_path, tag_tree, NewTag, Tag};
+use notor_core::{TagMerge, TagPatch};
 
 pub(crate) async fn get_tags(
-    filter: QueryFilter,
+    query: TagQuery,
     username: String,
     conn: Db,
 ) -> Result<impl Reply, Rejection> {
-    user_tags(filter, username, &conn)
+    let tags = user_tags(&query, username, &conn)
         .await
-        .map(|tags| reply::json(&tags))
+        .map_err(reject::custom)?;
+
+    if query.is_tree() {
+        Ok(reply::json(&tag_tree(tags)))
+    } else {
+        Ok(reply::json(&tags))
+    }
+}
+
+/// Loads the tag making sure that it belongs to the user.
+async fn authorized_tag(id: i32, username: &str, conn: &Db) -> Result<Tag, Rejection> {
+    let tag = load_tag(id, conn).await.map_err(reject::custom)?;
+
+    let user = load_user_from_id(tag.user_id, conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if user.username != username {
+        Err(reject::custom(Error::UnauthorizedAccess))
+    } else {
+        Ok(tag)
+    }
+}
+
+pub(crate) async fn get_tag(id: i32, username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    authorized_tag(id, &username, &conn)
+        .await
+        .map(|tag| reply::json(&tag))
+}
+
+/// Creates the tag along with its missing ancestors. The given color and description are
+/// set on the tag even when it exists already, omitted ones are left unchanged.
+pub(crate) async fn put_tag(
+    tag: NewTag,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    tag.check_owner(&username).map_err(reject::custom)?;
+    tag.validate().map_err(reject::custom)?;
+    let path = normalize_tag_path(&tag.name).map_err(reject::custom)?;
+
+    let id = find_or_create_tag(&path, &username, &conn)
+        .await
+        .map_err(reject::custom)?;
+    let details = TagPatch {
+        name: None,
+        color: tag.color.map(Some),
+        description: tag.description.map(Some),
+    };
+    _update_tag(id, &details, &conn)
+        .await
+        .map(|tag| reply::json(&tag))
+        .map_err(reject::custom)
+}
+
+/// Replaces the tag, nested tags follow it to the new path.
+pub(crate) async fn update_tag(
+    id: i32,
+    tag: NewTag,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    tag.check_owner(&username).map_err(reject::custom)?;
+    tag.validate().map_err(reject::custom)?;
+    let path = normalize_tag_path(&tag.name).map_err(reject::custom)?;
+    authorized_tag(id, &username, &conn).await?;
+
+    let patch = TagPatch {
+        name: Some(Some(path)),
+        color: Some(tag.color),
+        description: Some(tag.description),
+    };
+    _update_tag(id, &patch, &conn)
+        .await
+        .map(|tag| reply::json(&tag))
         .map_err(reject::custom)
 }
 
-pub(crate) async fn get_tag(id: i32, _: String, conn: Db) -> Result<impl Reply, Rejection> {
-    load_tag(id, &conn)
+pub(crate) async fn patch_tag(
+    id: i32,
+    mut patch: TagPatch,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    patch.validate().map_err(reject::custom)?;
+    if let Some(Some(name)) = &patch.name {
+        patch.name = Some(Some(normalize_tag_path(name).map_err(reject::custom)?));
+    }
+    authorized_tag(id, &username, &conn).await?;
+
+    _update_tag(id, &patch, &conn)
         .await
         .map(|tag| reply::json(&tag))
         .map_err(reject::custom)
 }
 
-pub(crate) async fn put_tag(tag: NewTag, _: String, conn: Db) -> Result<impl Reply, Rejection> {
-    save_tag(&tag, &conn)
+/// Merges the tag into the target tag, both have to belong to the user.
+pub(crate) async fn merge_tag(
+    id: i32,
+    merge: TagMerge,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_tag(id, &username, &conn).await?;
+    authorized_tag(merge.target_id, &username, &conn).await?;
+
+    merge_tags(id, merge.target_id, &conn)
         .await
         .map(|tag| reply::json(&tag))
         .map_err(reject::custom)
 }
 
-pub(crate) async fn delete_tag(id: i32, _: String, conn: Db) -> Result<impl Reply, Rejection> {
+/// Deletes the tag and its nested tags, they're removed from all notes of the user.
+pub(crate) async fn delete_tag(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_tag(id, &username, &conn).await?;
+
     _delete_tag(id, &conn)
         .await
         .map(|_| reply::reply())
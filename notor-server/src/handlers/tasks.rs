@@ -0,0 +1,17 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::db::Db;
+use crate::filters::TaskQuery;
+use crate::models::user_tasks;
+
+/// Lists tasks of all active notes of the user.
+pub(crate) async fn get_tasks(
+    query: TaskQuery,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    user_tasks(username, query.done, &conn)
+        .await
+        .map(|tasks| reply::json(&tasks))
+        .map_err(reject::custom)
+}
@@ -0,0 +1,108 @@
+use warp::{reject, reply, Rejection, Reply};
+
+use crate::auth::random_token;
+use crate::db::Db;
+use crate::models::{
+    delete_webhook as _delete_webhook, load_user_from_id, load_webhook, save_webhook,
+    update_webhook as _update_webhook, user_webhooks, webhook_deliveries,
+};
+use crate::webhooks::{check_target, Webhooks};
+use crate::Error;
+use notor_core::models::{NewWebhook, Webhook};
+
+/// Entries of the delivery log returned at most
+const DELIVERY_LOG_LEN: i64 = 50;
+
+/// Loads the webhook making sure that it belongs to the user.
+async fn authorized_webhook(id: i32, username: &str, conn: &Db) -> Result<Webhook, Rejection> {
+    let webhook = load_webhook(id, conn).await.map_err(reject::custom)?;
+
+    let user = load_user_from_id(webhook.user_id, conn)
+        .await
+        .map_err(reject::custom)?;
+
+    if user.username != username {
+        Err(reject::custom(Error::UnauthorizedAccess))
+    } else {
+        Ok(webhook)
+    }
+}
+
+pub(crate) async fn get_webhooks(username: String, conn: Db) -> Result<impl Reply, Rejection> {
+    user_webhooks(username, &conn)
+        .await
+        .map(|webhooks| reply::json(&webhooks))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn put_webhook(
+    webhook: NewWebhook,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    webhook.validate().map_err(reject::custom)?;
+    check_target(&webhook.url).await.map_err(reject::custom)?;
+
+    save_webhook(&webhook, &random_token(), &username, &conn)
+        .await
+        .map(|webhook| reply::json(&webhook))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn update_webhook(
+    id: i32,
+    webhook: NewWebhook,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    webhook.validate().map_err(reject::custom)?;
+    check_target(&webhook.url).await.map_err(reject::custom)?;
+    authorized_webhook(id, &username, &conn).await?;
+
+    _update_webhook(id, &webhook, &conn)
+        .await
+        .map(|webhook| reply::json(&webhook))
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn delete_webhook(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_webhook(id, &username, &conn).await?;
+
+    _delete_webhook(id, &conn)
+        .await
+        .map(|_| reply::reply())
+        .map_err(reject::custom)
+}
+
+pub(crate) async fn get_webhook_deliveries(
+    id: i32,
+    username: String,
+    conn: Db,
+) -> Result<impl Reply, Rejection> {
+    authorized_webhook(id, &username, &conn).await?;
+
+    webhook_deliveries(id, DELIVERY_LOG_LEN, &conn)
+        .await
+        .map(|deliveries| reply::json(&deliveries))
+        .map_err(reject::custom)
+}
+
+/// Sends a test event to the webhook and returns how the delivery went.
+pub(crate) async fn test_webhook(
+    id: i32,
+    username: String,
+    conn: Db,
+    webhooks: Webhooks,
+) -> Result<impl Reply, Rejection> {
+    let webhook = authorized_webhook(id, &username, &conn).await?;
+
+    webhooks
+        .test(&webhook)
+        .await
+        .map(|delivery| reply::json(&delivery))
+        .map_err(reject::custom)
+}
@@ -1,10 +1,16 @@
 mod auth;
+pub mod blobs;
 pub mod db;
+pub mod events;
 pub(crate) mod filters;
 mod handlers;
+pub mod live;
 mod models;
+pub mod notifiers;
 mod rejections;
+pub mod reminders;
 mod routes;
+pub mod webhooks;
 
 use notor_core::NotorError as Error;
 pub use routes::routes;
@@ -0,0 +1,526 @@
+use futures::{Future, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+use warp::ws::{Message, WebSocket};
+
+use crate::db::Db;
+use crate::events::Events;
+use crate::models::{load_note, save_live_content, save_revision};
+use crate::Error;
+use notor_core::crdt::{Item, Op, Snapshot, TextDoc, BASE_SITE};
+use notor_core::models::{Event, EventAction, EventKind};
+
+/// Time between the first unsaved edit and saving the content of the note
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+/// Times saving is retried right away when the note was changed in the meantime
+const SAVE_ATTEMPTS: usize = 3;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    /// Sent once after connecting, `site` has to be used in ids of inserted characters
+    Init {
+        site: u32,
+        clock: u64,
+        items: &'a [Item],
+        editable: bool,
+    },
+    Ops {
+        ops: &'a [Op],
+    },
+    /// Usernames of everyone who has the note open
+    Presence {
+        users: Vec<&'a str>,
+    },
+    /// Edits of the client were rejected, it has to reconnect to get the current state
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    Ops { ops: Vec<Op> },
+}
+
+struct Client {
+    username: String,
+    /// Whether the user can change the note rather than just view it
+    editable: bool,
+    tx: UnboundedSender<Message>,
+}
+
+struct Session {
+    doc: TextDoc,
+    /// Connected clients by their site
+    clients: HashMap<u32, Client>,
+    next_site: u32,
+    /// User who changed the note last, `None` until the note changes
+    editor: Option<String>,
+    /// Whether there are changes waiting to be saved
+    dirty: bool,
+    /// Characters of `doc` making up the saved content of the note
+    saved: Snapshot,
+    /// Held while the content is being saved so that saves are written in order
+    saving: Arc<AsyncMutex<()>>,
+}
+
+impl Session {
+    fn send(&self, site: u32, message: &ServerMessage) {
+        if let Some(client) = self.clients.get(&site) {
+            send(&client.tx, message);
+        }
+    }
+
+    /// Sends `message` to all clients except `except`.
+    fn broadcast(&self, message: &ServerMessage, except: Option<u32>) {
+        for (site, client) in &self.clients {
+            if Some(*site) != except {
+                send(&client.tx, message);
+            }
+        }
+    }
+
+    fn broadcast_presence(&self) {
+        let mut users: Vec<_> = self
+            .clients
+            .values()
+            .map(|client| client.username.as_str())
+            .collect();
+        users.sort_unstable();
+        users.dedup();
+
+        self.broadcast(&ServerMessage::Presence { users }, None);
+    }
+}
+
+fn send(tx: &UnboundedSender<Message>, message: &ServerMessage) {
+    match serde_json::to_string(message) {
+        // the client disconnecting is handled by its own task
+        Ok(text) => {
+            let _ = tx.send(Message::text(text));
+        }
+        Err(e) => log::error!("failed to serialize live message - {}", e),
+    }
+}
+
+/// Live editing sessions of open notes by the id of the note. Everyone with a note open
+/// is connected to its session which merges their edits, forwards them to the others and
+/// saves the merged content shortly after every change.
+///
+/// Saves only succeed if the saved content is still the one the session saved last.
+/// Content saved by anything else, like a session of the same note on another server
+/// instance, is merged into the session when its `note.updated` event arrives or when
+/// saving fails, so sessions on different instances converge with every save.
+#[derive(Clone, Default)]
+pub struct Sessions(Arc<Mutex<HashMap<i32, Session>>>);
+
+impl Sessions {
+    /// Starts merging content saved elsewhere into open sessions in the background.
+    pub fn start(db: Db, events: &Events) -> Self {
+        let sessions = Sessions::default();
+        tokio::spawn(sessions.clone().follow(events.subscribe_all(), db));
+        sessions
+    }
+
+    async fn follow(self, mut changes: Receiver<Event>, db: Db) {
+        loop {
+            let note_ids = match changes.recv().await {
+                Ok(Event {
+                    kind: EventKind::Note,
+                    action: EventAction::Updated,
+                    id,
+                    ..
+                }) => vec![id],
+                Ok(_) => continue,
+                // the missed events could be about any open note
+                Err(RecvError::Lagged(_)) => self.lock().keys().copied().collect(),
+                Err(RecvError::Closed) => return,
+            };
+
+            for note_id in note_ids {
+                if let Some(_saving) = self.lock_saving(note_id).await {
+                    self.merge_saved(note_id, &db).await;
+                }
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<i32, Session>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Waits until nothing else is saving the note and keeps others from saving it until
+    /// the returned guard is dropped. Returns `None` if the note isn't open.
+    async fn lock_saving(&self, note_id: i32) -> Option<OwnedMutexGuard<()>> {
+        let saving = self.lock().get(&note_id)?.saving.clone();
+        let guard = saving.clone().lock_owned().await;
+
+        // the session might have ended while waiting
+        match self.lock().get(&note_id) {
+            Some(session) if Arc::ptr_eq(&session.saving, &saving) => Some(guard),
+            _ => None,
+        }
+    }
+
+    /// Connects the user to the session of the note, starting it from the saved content
+    /// if nobody else has the note open, and handles their edits until they disconnect.
+    pub async fn connect(
+        self,
+        socket: WebSocket,
+        note_id: i32,
+        username: String,
+        editable: bool,
+        db: Db,
+    ) {
+        let (mut sink, mut stream) = socket.split();
+        let (tx, mut rx) = unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        });
+
+        // sessions stay open until their edits are saved, so this is only outdated if
+        // another session saved in between which the first save merges
+        let content = match load_note(note_id, &db).await {
+            Ok(note) => note.content.unwrap_or_default(),
+            Err(e) => {
+                let message = e.to_string();
+                return send(&tx, &ServerMessage::Error { message });
+            }
+        };
+        let site = self.join(note_id, &content, username, editable, tx.clone());
+
+        while let Some(Ok(message)) = stream.next().await {
+            if message.is_close() {
+                break;
+            }
+            // pings are answered by warp
+            let text = match message.to_str() {
+                Ok(text) => text,
+                Err(_) => continue,
+            };
+
+            let result = serde_json::from_str(text)
+                .map_err(|e| Error::InvalidEdit(e.to_string()))
+                .and_then(|ClientMessage::Ops { ops }| self.edit(note_id, site, &ops, &db));
+            if let Err(e) = result {
+                let message = e.to_string();
+                send(&tx, &ServerMessage::Error { message });
+            }
+        }
+
+        self.leave(note_id, site, &db).await;
+    }
+
+    fn join(
+        &self,
+        note_id: i32,
+        content: &str,
+        username: String,
+        editable: bool,
+        tx: UnboundedSender<Message>,
+    ) -> u32 {
+        let mut sessions = self.lock();
+        let session = sessions.entry(note_id).or_insert_with(|| {
+            let doc = TextDoc::from_text(content);
+            Session {
+                saved: doc.snapshot(),
+                doc,
+                clients: HashMap::new(),
+                next_site: 1,
+                editor: None,
+                dirty: false,
+                saving: Default::default(),
+            }
+        });
+
+        let site = session.next_site;
+        session.next_site += 1;
+        session.clients.insert(
+            site,
+            Client {
+                username,
+                editable,
+                tx,
+            },
+        );
+
+        session.send(
+            site,
+            &ServerMessage::Init {
+                site,
+                clock: session.doc.clock(),
+                items: session.doc.items(),
+                editable,
+            },
+        );
+        session.broadcast_presence();
+
+        site
+    }
+
+    /// Applies edits of the client `site` and forwards them to everyone else. Edits up to
+    /// the first invalid one are kept.
+    fn edit(&self, note_id: i32, site: u32, ops: &[Op], db: &Db) -> Result<(), Error> {
+        let mut sessions = self.lock();
+        let session = match sessions.get_mut(&note_id) {
+            Some(session) => session,
+            None => return Ok(()),
+        };
+        let username = match session.clients.get(&site) {
+            Some(client) if client.editable => client.username.clone(),
+            _ => return Err(Error::UnauthorizedAccess),
+        };
+
+        let mut applied = vec![];
+        let mut result = Ok(());
+        for op in ops {
+            if matches!(op.site(), Some(op_site) if op_site != site) {
+                result = Err(Error::InvalidEdit(format!(
+                    "inserted characters have to belong to site {}",
+                    site
+                )));
+                break;
+            }
+            match session.doc.apply(op) {
+                Ok(true) => applied.push(op.clone()),
+                Ok(false) => {}
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+
+        if !applied.is_empty() {
+            session.broadcast(&ServerMessage::Ops { ops: &applied }, Some(site));
+            session.editor = Some(username);
+            if !session.dirty {
+                session.dirty = true;
+                let (sessions, db) = (self.clone(), db.clone());
+                tokio::spawn(async move {
+                    tokio::time::sleep(SAVE_DELAY).await;
+                    sessions.save(note_id, &db).await;
+                });
+            }
+        }
+
+        result
+    }
+
+    /// Rewrites the content of the note if it's open so that the change reaches everyone
+    /// editing it instead of being overwritten by their next save, the result is saved
+    /// right away. Returns `None` if the note isn't open and whether `rewrite` changed the
+    /// content otherwise.
+    pub async fn rewrite<F>(&self, note_id: i32, editor: &str, rewrite: F, db: &Db) -> Option<bool>
+    where
+        F: FnOnce(&str) -> Option<String>,
+    {
+        let _saving = self.lock_saving(note_id).await?;
+        {
+            let mut sessions = self.lock();
+            let session = sessions.get_mut(&note_id)?;
+            let content = match rewrite(&session.doc.text()) {
+                Some(content) => content,
+                None => return Some(false),
+            };
+
+            let ops = session.doc.replace_text(&content, BASE_SITE);
+            session.broadcast(&ServerMessage::Ops { ops: &ops }, None);
+            session.editor = Some(editor.to_string());
+            session.dirty = true;
+        }
+
+        self.save_locked(note_id, db).await;
+        Some(true)
+    }
+
+    /// Changes the content of the note if it's open so that the change reaches everyone
+    /// editing it, `write` saves the note instead of the session. `rewrite` receives the
+    /// live content and returns the content to save, which is taken back if saving fails.
+    /// Returns `None` without calling `write` if the note isn't open.
+    pub async fn write<R, W, F, T>(
+        &self,
+        note_id: i32,
+        editor: &str,
+        rewrite: R,
+        write: W,
+    ) -> Option<Result<T, Error>>
+    where
+        R: FnOnce(&str) -> Result<Option<String>, Error>,
+        W: FnOnce(Option<String>) -> F,
+        F: Future<Output = Result<T, Error>>,
+    {
+        let _saving = self.lock_saving(note_id).await?;
+        let (content, mut written, previous, dirty) = {
+            let mut sessions = self.lock();
+            let session = sessions.get_mut(&note_id)?;
+            let previous = session.doc.text();
+            let content = match rewrite(&previous) {
+                Ok(content) => content,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let ops = session
+                .doc
+                .replace_text(content.as_deref().unwrap_or_default(), BASE_SITE);
+            session.broadcast(&ServerMessage::Ops { ops: &ops }, None);
+            let dirty = std::mem::replace(&mut session.dirty, false);
+            (content, session.doc.snapshot(), previous, dirty)
+        };
+
+        // boxed, saving the whole note overflows the stack of debug builds otherwise
+        let result = Box::pin(write(content)).await;
+
+        let mut sessions = self.lock();
+        if let Some(session) = sessions.get_mut(&note_id) {
+            if result.is_ok() {
+                session.saved = written;
+                session.editor = Some(editor.to_string());
+            } else {
+                // edits made while writing are kept
+                let ops = session
+                    .doc
+                    .replace_since(&mut written, &previous, BASE_SITE);
+                session.broadcast(&ServerMessage::Ops { ops: &ops }, None);
+                session.dirty |= dirty;
+            }
+        }
+        Some(result)
+    }
+
+    async fn save(&self, note_id: i32, db: &Db) {
+        if let Some(_saving) = self.lock_saving(note_id).await {
+            self.save_locked(note_id, db).await;
+        }
+    }
+
+    /// Saves the edits of the session, content saved by anything else in the meantime is
+    /// merged first. Has to be called while holding [`Sessions::lock_saving`], returns
+    /// whether all edits are saved.
+    async fn save_locked(&self, note_id: i32, db: &Db) -> bool {
+        for _ in 0..SAVE_ATTEMPTS {
+            let pending = {
+                let mut sessions = self.lock();
+                match sessions.get_mut(&note_id) {
+                    Some(session) if session.dirty => {
+                        session.dirty = false;
+                        session
+                            .editor
+                            .clone()
+                            .map(|editor| (session.doc.snapshot(), session.saved.text(), editor))
+                    }
+                    _ => None,
+                }
+            };
+            let (snapshot, base, editor) = match pending {
+                Some(pending) => pending,
+                None => return true,
+            };
+
+            let result = save_live_content(note_id, &snapshot.text(), &base, &editor, db).await;
+            {
+                let mut sessions = self.lock();
+                let session = match sessions.get_mut(&note_id) {
+                    Some(session) => session,
+                    None => return false,
+                };
+                match result {
+                    Ok(true) => {
+                        session.saved = snapshot;
+                        return true;
+                    }
+                    // merged and saved again below
+                    Ok(false) => session.dirty = true,
+                    Err(e) => {
+                        session.dirty = true;
+                        log::warn!("failed to save live content of note {} - {}", note_id, e);
+                        return false;
+                    }
+                }
+            }
+            self.merge_saved(note_id, db).await;
+        }
+
+        log::warn!(
+            "failed to save live content of note {} - it keeps changing",
+            note_id
+        );
+        false
+    }
+
+    /// Merges the saved content of the note into its session if anything else changed it,
+    /// has to be called while holding [`Sessions::lock_saving`].
+    async fn merge_saved(&self, note_id: i32, db: &Db) {
+        let content = match load_note(note_id, db).await {
+            Ok(note) => note.content.unwrap_or_default(),
+            Err(e) => {
+                return log::warn!("failed to load saved content of note {} - {}", note_id, e)
+            }
+        };
+
+        let mut sessions = self.lock();
+        if let Some(session) = sessions.get_mut(&note_id) {
+            if session.saved.text() != content {
+                let ops = session
+                    .doc
+                    .replace_since(&mut session.saved, &content, BASE_SITE);
+                session.broadcast(&ServerMessage::Ops { ops: &ops }, None);
+            }
+        }
+    }
+
+    /// Disconnects the client, the last one to leave ends the session saving the content
+    /// of the note along with a revision if it changed. The session stays open until then
+    /// so that anyone opening the note in the meantime joins it.
+    async fn leave(&self, note_id: i32, site: u32, db: &Db) {
+        {
+            let mut sessions = self.lock();
+            let session = match sessions.get_mut(&note_id) {
+                Some(session) => session,
+                None => return,
+            };
+            session.clients.remove(&site);
+            if !session.clients.is_empty() {
+                return session.broadcast_presence();
+            }
+        }
+
+        let _saving = match self.lock_saving(note_id).await {
+            Some(saving) => saving,
+            None => return,
+        };
+        let all_saved = self.save_locked(note_id, db).await;
+
+        let ended = {
+            let mut sessions = self.lock();
+            match sessions.get(&note_id) {
+                Some(session) if session.clients.is_empty() => sessions.remove(&note_id),
+                _ => None,
+            }
+        };
+        if let Some(Session {
+            saved,
+            editor: Some(editor),
+            ..
+        }) = ended.filter(|_| all_saved)
+        {
+            if let Err(e) = save_revision(note_id, &saved.text(), &editor, db).await {
+                log::warn!("failed to save revision of note {} - {}", note_id, e);
+            }
+        }
+    }
+}
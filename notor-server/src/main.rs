@@ -1,10 +1,29 @@
+use notor::events::Events;
+use notor::live::Sessions;
+use notor::webhooks::Webhooks;
+
 #[tokio::main]
 async fn main() {
     pretty_env_logger::init();
 
+    let blobs = match notor::blobs::blob_store() {
+        Ok(blobs) => blobs,
+        Err(e) => return eprintln!("{}", e),
+    };
+
     match notor::db::db_connection().await {
         Ok(conn) => {
-            warp::serve(notor::routes(conn))
+            let notifiers = match notor::notifiers::notifiers(&conn) {
+                Ok(notifiers) => notifiers,
+                Err(e) => return eprintln!("{}", e),
+            };
+
+            let events = Events::listen(conn.clone());
+            let webhooks = Webhooks::start(conn.clone(), &events);
+            let sessions = Sessions::start(conn.clone(), &events);
+            notor::reminders::start(conn.clone(), notifiers);
+
+            warp::serve(notor::routes(conn, blobs, events, webhooks, sessions))
                 .run(([127, 0, 0, 1], 3693))
                 .await;
         }
@@ -1,56 +1,105 @@
-use crate::db::DbConn;
-use crate::filters::QueryFilter;
+use crate::blobs::Blobs;
+use crate::db::{DbConn, DbTx};
+use crate::filters::{QueryFilter, SortOrder, TagQuery};
 use crate::Error;
+use notor_core::graph::{Graph, GraphEdge, GraphNode};
 use notor_core::models::*;
+use notor_core::{
+    check_link_title, rewrite_title_links, task_items, toggle_task, wiki_links, CalendarEntry,
+    NotePatch, TagPatch, WikiLink,
+};
 
 pub async fn load_notes<S: AsRef<str>>(
     filter: QueryFilter,
     username: S,
     conn: &DbConn,
 ) -> Result<Vec<Note>, Error> {
-    let limit = if let Some(l) = filter.limit {
-        l
-    } else {
-        i64::MAX
-    };
+    let limit = filter.limit.unwrap_or(i64::MAX);
+    let sort = filter.sort.unwrap_or_default();
+    let ascending = filter.order.unwrap_or_default() == SortOrder::Asc;
+    let tags = filter.tag_expression()?;
 
-    if let Some(tag) = filter.tag_id {
-        sqlx::query_as!(
-            Note,
-            r#"
-    SELECT notes.id, users.id as "user_id: _", notes.created, title, content
-    FROM notes
-    INNER JOIN notes_tags on notes_tags.note_id = notes.id
-    INNER JOIN users on users.id = notes.user_id
-    WHERE notes_tags.tag_id = $1 AND username = $2
-    ORDER BY notes.id
-    LIMIT $3
-                "#,
-            tag,
-            username.as_ref(),
-            limit
-        )
-        .fetch_all(conn)
-        .await
-        .map_err(Error::from)
-    } else {
-        sqlx::query_as!(
-            Note,
-            r#"
-    SELECT notes.id, users.id as "user_id: _", notes.created, title, content
-    FROM notes
-    INNER JOIN users on users.id = notes.user_id
-    WHERE username = $1
-    ORDER BY notes.id
-    LIMIT $2
-                "#,
-            username.as_ref(),
-            limit
+    sqlx::query_as!(
+        Note,
+        r#"
+SELECT
+    notes.id, users.id as "user_id: _", notes.created, title, content,
+    notes.updated, notes.updated_by, notes.notebook_id, notes.pinned, notes.favorite,
+    notes.archived, notes.due_at, notes.remind_at, notes.reminded
+FROM notes
+INNER JOIN users on users.id = notes.user_id
+LEFT JOIN users AS editors on editors.id = notes.updated_by
+WHERE users.username = $1
+    AND ( $2::INT IS NULL OR EXISTS (
+        SELECT 1
+        FROM notes_tags
+        INNER JOIN tags ON tags.id = notes_tags.tag_id
+        INNER JOIN tags AS filter_tag ON filter_tag.id = $2
+        WHERE notes_tags.note_id = notes.id
+            AND tags.user_id = filter_tag.user_id
+            AND ( tags.id = filter_tag.id OR starts_with(tags.name, filter_tag.name || '/') )
+    ))
+    AND ( $6::INT IS NULL OR notes.notebook_id = $6 )
+    AND ( COALESCE($7::TEXT, '') = '' OR to_tsvector('simple', notes.title || ' ' || COALESCE(notes.content, ''))
+        @@ websearch_to_tsquery('simple', $7) )
+    AND ( $8::TIMESTAMPTZ IS NULL OR notes.created >= $8 )
+    AND ( $9::TIMESTAMPTZ IS NULL OR notes.created < $9 )
+    AND NOT EXISTS (
+        SELECT 1 FROM unnest($10::TEXT[]) AS required(path)
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM notes_tags
+            INNER JOIN tags ON tags.id = notes_tags.tag_id
+            WHERE notes_tags.note_id = notes.id
+                AND ( tags.name = required.path OR starts_with(tags.name, required.path || '/') )
         )
-        .fetch_all(conn)
-        .await
-        .map_err(Error::from)
-    }
+    )
+    AND NOT EXISTS (
+        SELECT 1
+        FROM notes_tags
+        INNER JOIN tags ON tags.id = notes_tags.tag_id
+        INNER JOIN unnest($11::TEXT[]) AS excluded(path)
+            ON tags.name = excluded.path OR starts_with(tags.name, excluded.path || '/')
+        WHERE notes_tags.note_id = notes.id
+    )
+    AND ( $12::BOOL IS NULL OR notes.pinned = $12 )
+    AND ( $13::BOOL IS NULL OR notes.favorite = $13 )
+    AND CASE
+        WHEN $15::BOOL IS NOT NULL THEN ( notes.archived IS NOT NULL ) = $15
+        ELSE $16 OR notes.archived IS NULL
+    END
+ORDER BY
+    CASE WHEN $14 THEN notes.pinned END DESC,
+    CASE WHEN $3 = 'created' AND $4 THEN notes.created END ASC,
+    CASE WHEN $3 = 'created' AND NOT $4 THEN notes.created END DESC,
+    CASE WHEN $3 = 'updated' AND $4 THEN COALESCE(notes.updated, notes.created) END ASC,
+    CASE WHEN $3 = 'updated' AND NOT $4 THEN COALESCE(notes.updated, notes.created) END DESC,
+    CASE WHEN $3 = 'updated_by' AND $4 THEN editors.username END ASC,
+    CASE WHEN $3 = 'updated_by' AND NOT $4 THEN editors.username END DESC,
+    CASE WHEN $4 THEN notes.id END ASC,
+    CASE WHEN NOT $4 THEN notes.id END DESC
+LIMIT $5
+            "#,
+        username.as_ref(),
+        filter.tag_id,
+        sort.as_ref(),
+        ascending,
+        limit,
+        filter.notebook_id,
+        filter.q,
+        filter.created_after,
+        filter.created_before,
+        &tags.required,
+        &tags.excluded,
+        filter.pinned,
+        filter.favorite,
+        filter.pinned_first.unwrap_or(true),
+        filter.archived,
+        filter.include_archived.unwrap_or(false)
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
 }
 
 #[allow(dead_code)]
@@ -60,8 +109,8 @@ pub async fn load_notes_with_tags<S: AsRef<str>>(
     conn: &DbConn,
 ) -> Result<Vec<NoteWithTags>, Error> {
     let mut notes_with_tags = Vec::new();
-    for note in load_notes(filter, username, &conn).await? {
-        let tags = note_tags(note.id, &conn).await?;
+    for note in load_notes(filter, username, conn).await? {
+        let tags = note_tags(note.id, conn).await?;
 
         notes_with_tags.push((note, tags));
     }
@@ -84,209 +133,2179 @@ WHERE id = $1
     .map_err(Error::from)
 }
 
-pub async fn save_note(note: &NewNote, conn: &DbConn) -> Result<Note, Error> {
-    sqlx::query_as!(
+pub async fn save_note<S: AsRef<str>>(
+    note: &NewNote,
+    owner: S,
+    conn: &DbConn,
+) -> Result<Note, Error> {
+    let mut tx = conn.begin().await?;
+
+    let note = sqlx::query_as!(
         Note,
         "
-INSERT INTO notes ( created, title, content, user_id )
-VALUES ( $1, $2, $3, ( SELECT id FROM users WHERE users.username = $4 ) )
+INSERT INTO notes ( created, title, content, user_id, notebook_id )
+VALUES ( $1, $2, $3, ( SELECT id FROM users WHERE users.username = $4 ), $5 )
 RETURNING *
             ",
-        chrono::offset::Utc::now().naive_utc(),
+        chrono::offset::Utc::now(),
         note.title,
         note.content,
-        note.username,
+        owner.as_ref(),
+        note.notebook_id,
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    set_note_links(note.id, note.user_id, note.content.as_deref(), &mut tx).await?;
+    set_note_tasks(note.id, note.content.as_deref(), &mut tx).await?;
+    relink_title(&note, None, None, &mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(note)
+}
+
+/// Deletes the note, its tag links and attachments are removed by the database. Contents
+/// of the attachments are removed from the blob store afterwards.
+pub async fn delete_note(id: i32, blobs: &Blobs, conn: &DbConn) -> Result<(), Error> {
+    let attachments = note_attachments(id, conn).await?;
+
+    sqlx::query!(
+        "
+DELETE FROM notes
+WHERE id = $1
+            ",
+        id
+    )
+    .execute(conn)
+    .await?;
+
+    for attachment in attachments {
+        delete_blob(&attachment, blobs).await;
+    }
+
+    Ok(())
+}
+
+/// Replaces the note with `new_note`. With `rewrite_links` set, links to the old title
+/// of a renamed note are rewritten to its new title.
+pub async fn update_note<S: AsRef<str>>(
+    id: i32,
+    new_note: &NewNote,
+    rewrite_links: bool,
+    editor: S,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    let old_title = sqlx::query!(
+        "
+SELECT title
+FROM notes
+WHERE id = $1
+FOR UPDATE
+            ",
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .title;
+
+    let note = sqlx::query_as!(
+        Note,
+        "
+UPDATE notes
+SET ( title, content, updated, updated_by ) =
+    ( $1, $2, $3, ( SELECT id FROM users WHERE username = $4 ) )
+WHERE id = $5
+RETURNING *
+            ",
+        new_note.title,
+        new_note.content,
+        chrono::offset::Utc::now(),
+        editor.as_ref(),
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    set_note_links(id, note.user_id, note.content.as_deref(), &mut tx).await?;
+    set_note_tasks(id, note.content.as_deref(), &mut tx).await?;
+    if note.title != old_title {
+        let rewrite_by = Some(editor.as_ref()).filter(|_| rewrite_links);
+        relink_title(&note, Some(&old_title), rewrite_by, &mut tx).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Applies `patch` to the note in a single transaction and returns the updated note.
+/// See [`update_note`] for `rewrite_links`.
+pub async fn patch_note<S: AsRef<str>>(
+    id: i32,
+    patch: &NotePatch,
+    rewrite_links: bool,
+    editor: S,
+    conn: &DbConn,
+) -> Result<Note, Error> {
+    let mut tx = conn.begin().await?;
+
+    let note = sqlx::query_as!(
+        Note,
+        "
+SELECT *
+FROM notes
+WHERE id = $1
+FOR UPDATE
+            ",
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    let old_title = note.title;
+    let title = patch.patched_title(old_title.clone());
+    let content = patch.patched_content(note.content)?;
+    let notebook_id = patch.notebook_id.unwrap_or(note.notebook_id);
+    let pinned = patch.pinned.unwrap_or(note.pinned);
+    let favorite = patch.favorite.unwrap_or(note.favorite);
+    let due_at = patch.due_at.unwrap_or(note.due_at);
+    let remind_at = patch.remind_at.unwrap_or(note.remind_at);
+    let reminded = if remind_at == note.remind_at {
+        note.reminded
+    } else {
+        None
+    };
+
+    sqlx::query!(
+        "
+UPDATE notes
+SET (
+    title, content, updated, updated_by, notebook_id, pinned, favorite, due_at, remind_at,
+    reminded
+) = ( $1, $2, $3, ( SELECT id FROM users WHERE username = $4 ), $5, $6, $7, $8, $9, $10 )
+WHERE id = $11
+            ",
+        title,
+        content,
+        chrono::offset::Utc::now(),
+        editor.as_ref(),
+        notebook_id,
+        pinned,
+        favorite,
+        due_at,
+        remind_at,
+        reminded,
+        id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    if let Some(tags) = &patch.tags {
+        let names = tags.as_deref().unwrap_or_default();
+        set_note_tags(note.user_id, id, names, &mut tx).await?;
+    }
+
+    let note = sqlx::query_as!(
+        Note,
+        "
+SELECT *
+FROM notes
+WHERE id = $1
+            ",
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    set_note_links(id, note.user_id, note.content.as_deref(), &mut tx).await?;
+    set_note_tasks(id, note.content.as_deref(), &mut tx).await?;
+    if note.title != old_title {
+        let rewrite_by = Some(editor.as_ref()).filter(|_| rewrite_links);
+        relink_title(&note, Some(&old_title), rewrite_by, &mut tx).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(note)
+}
+
+/// Replaces all tags of the note with tags named `names` owned by `user_id`, creating
+/// the ones that don't exist yet.
+async fn set_note_tags(
+    user_id: i32,
+    note_id: i32,
+    names: &[String],
+    tx: &mut DbTx<'_>,
+) -> Result<(), Error> {
+    let mut names = names
+        .iter()
+        .map(|name| normalize_tag_path(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    names.sort();
+    names.dedup();
+
+    sqlx::query!(
+        "
+DELETE FROM notes_tags
+WHERE note_id = $1
+            ",
+        note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for name in names {
+        let tag_id = create_tag_path(user_id, &name, tx).await?;
+        sqlx::query!(
+            "
+INSERT INTO notes_tags ( note_id, tag_id )
+VALUES ( $1, $2 )
+            ",
+            note_id,
+            tag_id
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Replaces all outgoing links of the note with links found in its `content`. Links
+/// to notes that don't exist or belong to another user are skipped.
+async fn set_note_links(
+    note_id: i32,
+    user_id: i32,
+    content: Option<&str>,
+    tx: &mut DbTx<'_>,
+) -> Result<(), Error> {
+    let mut ids = vec![];
+    let mut titles = vec![];
+    for (_, link) in wiki_links(content.unwrap_or_default()) {
+        match link {
+            WikiLink::Id(id) => ids.push(id),
+            WikiLink::Title(title) => titles.push(title),
+        }
+    }
+
+    sqlx::query!(
+        "
+DELETE FROM note_links
+WHERE source_id = $1
+            ",
+        note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO note_links ( source_id, target_id )
+SELECT $1, id
+FROM notes
+WHERE user_id = $2 AND id <> $1 AND ( id = ANY($3) OR title = ANY($4) )
+            ",
+        note_id,
+        user_id,
+        &ids,
+        &titles
+    )
+    .execute(&mut *tx)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Replaces all tasks of the note with task list items found in its `content`.
+async fn set_note_tasks(
+    note_id: i32,
+    content: Option<&str>,
+    tx: &mut DbTx<'_>,
+) -> Result<(), Error> {
+    let items = task_items(content.unwrap_or_default());
+    let texts: Vec<_> = items.iter().map(|item| item.text.clone()).collect();
+    let done: Vec<_> = items.iter().map(|item| item.done).collect();
+
+    sqlx::query!(
+        "
+DELETE FROM note_tasks
+WHERE note_id = $1
+            ",
+        note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO note_tasks ( note_id, position, text, done )
+SELECT $1, ( task.n - 1 )::INT, task.text, task.done
+FROM UNNEST($2::TEXT[], $3::BOOLEAN[]) WITH ORDINALITY AS task(text, done, n)
+            ",
+        note_id,
+        &texts,
+        &done
+    )
+    .execute(&mut *tx)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Loads tasks of the note in the order they appear in its content.
+pub async fn note_tasks(note_id: i32, conn: &DbConn) -> Result<Vec<NoteTask>, Error> {
+    sqlx::query_as!(
+        NoteTask,
+        "
+SELECT note_tasks.note_id, notes.title as note_title, position, text, done
+FROM note_tasks
+INNER JOIN notes on notes.id = note_tasks.note_id
+WHERE note_id = $1
+ORDER BY position
+            ",
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Loads tasks of all active notes of the user, only open or only finished ones if
+/// `done` is set. Tasks of recently edited notes come first.
+pub async fn user_tasks<S: AsRef<str>>(
+    username: S,
+    done: Option<bool>,
+    conn: &DbConn,
+) -> Result<Vec<NoteTask>, Error> {
+    sqlx::query_as!(
+        NoteTask,
+        "
+SELECT note_tasks.note_id, notes.title as note_title, position, text, done
+FROM note_tasks
+INNER JOIN notes on notes.id = note_tasks.note_id
+INNER JOIN users on users.id = notes.user_id
+WHERE users.username = $1 AND notes.archived IS NULL AND ( $2::BOOLEAN IS NULL OR done = $2 )
+ORDER BY COALESCE(notes.updated, notes.created) DESC, note_tasks.note_id, position
+            ",
+        username.as_ref(),
+        done
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_note_task(note_id: i32, position: i32, conn: &DbConn) -> Result<NoteTask, Error> {
+    sqlx::query_as!(
+        NoteTask,
+        "
+SELECT note_tasks.note_id, notes.title as note_title, position, text, done
+FROM note_tasks
+INNER JOIN notes on notes.id = note_tasks.note_id
+WHERE note_id = $1 AND position = $2
+            ",
+        note_id,
+        position
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Checks or unchecks the task at `position` by rewriting the content of the note as an
+/// edit of `editor`.
+pub async fn toggle_note_task(
+    note_id: i32,
+    position: usize,
+    editor: &str,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    let content = sqlx::query!(
+        "
+SELECT content
+FROM notes
+WHERE id = $1
+FOR UPDATE
+            ",
+        note_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .content;
+
+    let toggled = content
+        .and_then(|content| toggle_task(&content, position))
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    sqlx::query!(
+        "
+UPDATE notes
+SET content = $1
+WHERE id = $2
+            ",
+        toggled,
+        note_id
+    )
+    .execute(&mut tx)
+    .await?;
+    touch_note(note_id, editor, &mut tx).await?;
+    set_note_tasks(note_id, Some(&toggled), &mut tx).await?;
+
+    tx.commit().await.map_err(Error::from)
+}
+
+/// Refreshes links of notes referring to `note` by title after it was created or
+/// renamed from `old_title`. With `rewrite_by` set, `[[old title]]` links are rewritten
+/// to the new title as an edit made by that user.
+async fn relink_title(
+    note: &Note,
+    old_title: Option<&str>,
+    rewrite_by: Option<&str>,
+    tx: &mut DbTx<'_>,
+) -> Result<(), Error> {
+    if rewrite_by.is_some() {
+        check_link_title(&note.title)?;
+    }
+
+    // candidates mention the title or the id, the rest already links the note
+    let sources = sqlx::query!(
+        "
+SELECT id, content
+FROM notes
+WHERE user_id = $1 AND id <> $2 AND (
+    strpos(content, $3) > 0
+    OR strpos(content, '#' || $2) > 0
+    OR id IN ( SELECT source_id FROM note_links WHERE target_id = $2 )
+)
+FOR UPDATE
+            ",
+        note.user_id,
+        note.id,
+        note.title
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for source in sources {
+        let mut content = source.content;
+
+        if let (Some(old_title), Some(editor)) = (old_title, rewrite_by) {
+            let rewritten = match content.as_deref() {
+                Some(content) => rewrite_title_links(content, old_title, &note.title)?,
+                None => None,
+            };
+            if let Some(rewritten) = rewritten {
+                sqlx::query!(
+                    "
+UPDATE notes
+SET content = $1
+WHERE id = $2
+            ",
+                    rewritten,
+                    source.id
+                )
+                .execute(&mut *tx)
+                .await?;
+                touch_note(source.id, editor, tx).await?;
+                set_note_tasks(source.id, Some(&rewritten), tx).await?;
+                content = Some(rewritten);
+            }
+        }
+
+        set_note_links(source.id, note.user_id, content.as_deref(), tx).await?;
+    }
+
+    Ok(())
+}
+
+/// Loads notes linking to the note `note_id` which the user `viewer` owns or can read.
+pub async fn note_backlinks<S: AsRef<str>>(
+    note_id: i32,
+    viewer: S,
+    conn: &DbConn,
+) -> Result<Vec<Note>, Error> {
+    sqlx::query_as!(
+        Note,
+        "
+SELECT notes.*
+FROM notes
+INNER JOIN note_links ON note_links.source_id = notes.id
+INNER JOIN users AS viewer ON viewer.username = $2
+WHERE note_links.target_id = $1
+  AND ( notes.user_id = viewer.id
+        OR notes.id IN ( SELECT note_id FROM note_shares WHERE user_id = viewer.id ) )
+ORDER BY notes.title, notes.id
+            ",
+        note_id,
+        viewer.as_ref()
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Loads notes linked from the content of the note `note_id` which the user `viewer`
+/// owns or can read.
+pub async fn note_links<S: AsRef<str>>(
+    note_id: i32,
+    viewer: S,
+    conn: &DbConn,
+) -> Result<Vec<Note>, Error> {
+    sqlx::query_as!(
+        Note,
+        "
+SELECT notes.*
+FROM notes
+INNER JOIN note_links ON note_links.target_id = notes.id
+INNER JOIN users AS viewer ON viewer.username = $2
+WHERE note_links.source_id = $1
+  AND ( notes.user_id = viewer.id
+        OR notes.id IN ( SELECT note_id FROM note_shares WHERE user_id = viewer.id ) )
+ORDER BY notes.id
+            ",
+        note_id,
+        viewer.as_ref()
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Saves the content merged by a live editing session as an edit of `editor` unless the
+/// saved content isn't `base` anymore. Returns whether the content was saved, a missing
+/// content counts as empty.
+pub async fn save_live_content(
+    note_id: i32,
+    content: &str,
+    base: &str,
+    editor: &str,
+    conn: &DbConn,
+) -> Result<bool, Error> {
+    let mut tx = conn.begin().await?;
+
+    let saved = sqlx::query!(
+        "
+UPDATE notes
+SET ( content, updated, updated_by ) =
+    ( $1, $2, ( SELECT id FROM users WHERE username = $3 ) )
+WHERE id = $4 AND COALESCE(content, '') = $5
+RETURNING user_id
+            ",
+        content,
+        chrono::offset::Utc::now(),
+        editor,
+        note_id,
+        base
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let user_id = match saved {
+        Some(saved) => saved.user_id,
+        None => return Ok(false),
+    };
+
+    set_note_links(note_id, user_id, Some(content), &mut tx).await?;
+    set_note_tasks(note_id, Some(content), &mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+pub async fn save_revision(
+    note_id: i32,
+    content: &str,
+    editor: &str,
+    conn: &DbConn,
+) -> Result<NoteRevision, Error> {
+    sqlx::query_as!(
+        NoteRevision,
+        "
+INSERT INTO note_revisions ( note_id, user_id, created, content )
+VALUES ( $1, ( SELECT id FROM users WHERE username = $2 ), $3, $4 )
+RETURNING *
+            ",
+        note_id,
+        editor,
+        chrono::offset::Utc::now(),
+        content
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Loads revisions of the note, newest first.
+pub async fn note_revisions(note_id: i32, conn: &DbConn) -> Result<Vec<NoteRevision>, Error> {
+    sqlx::query_as!(
+        NoteRevision,
+        "
+SELECT *
+FROM note_revisions
+WHERE note_id = $1
+ORDER BY created DESC, id DESC
+            ",
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Marks the note as modified by `editor` right now.
+async fn touch_note(note_id: i32, editor: &str, tx: &mut DbTx<'_>) -> Result<(), Error> {
+    sqlx::query!(
+        "
+UPDATE notes
+SET ( updated, updated_by ) = ( $1, ( SELECT id FROM users WHERE username = $2 ) )
+WHERE id = $3
+            ",
+        chrono::offset::Utc::now(),
+        editor,
+        note_id
+    )
+    .execute(tx)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Pins or unpins the note, pinning doesn't count as an edit of the note.
+pub async fn pin_note(note_id: i32, pinned: bool, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+UPDATE notes
+SET pinned = $1
+WHERE id = $2
+            ",
+        pinned,
+        note_id
+    )
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Marks the note as a favorite of its owner or removes the mark.
+pub async fn favorite_note(note_id: i32, favorite: bool, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+UPDATE notes
+SET favorite = $1
+WHERE id = $2
+            ",
+        favorite,
+        note_id
+    )
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Archives the note or brings it back when `archive` is `false`. Archiving doesn't
+/// count as an edit of the note.
+pub async fn archive_note(note_id: i32, archive: bool, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+UPDATE notes
+SET archived = CASE WHEN $1 THEN COALESCE(archived, $2) END
+WHERE id = $3
+            ",
+        archive,
+        chrono::offset::Utc::now(),
+        note_id
+    )
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Files the note in `notebook_id` or takes it out of its notebook when `None`.
+pub async fn move_note<S: AsRef<str>>(
+    note_id: i32,
+    notebook_id: Option<i32>,
+    editor: S,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "
+UPDATE notes
+SET notebook_id = $1
+WHERE id = $2
+            ",
+        notebook_id,
+        note_id
+    )
+    .execute(&mut tx)
+    .await?;
+    touch_note(note_id, editor.as_ref(), &mut tx).await?;
+
+    tx.commit().await.map_err(Error::from)
+}
+
+pub async fn tag_note<S: AsRef<str>>(
+    note_id: i32,
+    tag_id: i32,
+    editor: S,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "
+INSERT INTO notes_tags ( note_id, tag_id )
+VALUES ( $1, $2 )
+            ",
+        note_id,
+        tag_id
+    )
+    .execute(&mut tx)
+    .await?;
+    touch_note(note_id, editor.as_ref(), &mut tx).await?;
+
+    tx.commit().await.map_err(Error::from)
+}
+
+pub async fn untag_note<S: AsRef<str>>(
+    note_id: i32,
+    tag_id: i32,
+    editor: S,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query!(
+        "
+DELETE FROM notes_tags
+WHERE note_id = $1 AND tag_id = $2
+            ",
+        note_id,
+        tag_id
+    )
+    .execute(&mut tx)
+    .await?;
+    touch_note(note_id, editor.as_ref(), &mut tx).await?;
+
+    tx.commit().await.map_err(Error::from)
+}
+
+pub async fn note_tags(note_id: i32, conn: &DbConn) -> Result<Vec<Tag>, Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+SELECT tags.id, u.id as "user_id: _", name, color, description
+FROM tags
+INNER JOIN notes_tags AS nt ON nt.tag_id = tags.id
+INNER JOIN users AS u ON u.id = tags.user_id
+WHERE nt.note_id = $1
+            "#,
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Permission the user `username` was given to a note of someone else, `None` when the
+/// note isn't shared with them.
+pub async fn note_permission<S: AsRef<str>>(
+    note_id: i32,
+    username: S,
+    conn: &DbConn,
+) -> Result<Option<SharePermission>, Error> {
+    sqlx::query!(
+        r#"
+SELECT permission as "permission: SharePermission"
+FROM note_shares
+INNER JOIN users ON users.id = note_shares.user_id
+WHERE note_shares.note_id = $1 AND users.username = $2
+            "#,
+        note_id,
+        username.as_ref()
+    )
+    .fetch_optional(conn)
+    .await
+    .map(|row| row.map(|row| row.permission))
+    .map_err(Error::from)
+}
+
+pub async fn note_shares(note_id: i32, conn: &DbConn) -> Result<Vec<NoteShare>, Error> {
+    sqlx::query_as!(
+        NoteShare,
+        r#"
+SELECT note_id, user_id, users.username, permission as "permission: _", note_shares.created
+FROM note_shares
+INNER JOIN users ON users.id = note_shares.user_id
+WHERE note_id = $1
+ORDER BY users.username
+            "#,
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Shares the note with `share.username` or changes the permission if it's shared with
+/// them already. Fails with `RowNotFound` when there is no such user.
+pub async fn share_note(
+    note_id: i32,
+    share: &NewNoteShare,
+    conn: &DbConn,
+) -> Result<NoteShare, Error> {
+    sqlx::query_as!(
+        NoteShare,
+        r#"
+WITH share AS (
+    INSERT INTO note_shares ( note_id, user_id, permission, created )
+    SELECT $1, users.id, $3, $4
+    FROM users
+    WHERE users.username = $2
+    ON CONFLICT ( note_id, user_id ) DO UPDATE SET permission = EXCLUDED.permission
+    RETURNING *
+)
+SELECT
+    share.note_id as "note_id!",
+    share.user_id as "user_id!",
+    users.username as "username!",
+    share.permission as "permission!: _",
+    share.created as "created!"
+FROM share
+INNER JOIN users ON users.id = share.user_id
+            "#,
+        note_id,
+        share.username,
+        share.permission as _,
+        chrono::offset::Utc::now()
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn unshare_note(note_id: i32, user_id: i32, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM note_shares
+WHERE note_id = $1 AND user_id = $2
+            ",
+        note_id,
+        user_id
+    )
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Loads notes of other users shared with the user `username`, most recently changed
+/// first. Archived notes are left out.
+pub async fn shared_notes<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<Vec<SharedNote>, Error> {
+    let rows = sqlx::query!(
+        r#"
+SELECT notes.*, owner.username as owner, note_shares.permission as "permission: SharePermission"
+FROM notes
+INNER JOIN note_shares ON note_shares.note_id = notes.id
+INNER JOIN users AS viewer ON viewer.id = note_shares.user_id
+INNER JOIN users AS owner ON owner.id = notes.user_id
+WHERE viewer.username = $1 AND notes.archived IS NULL
+ORDER BY COALESCE(notes.updated, notes.created) DESC, notes.id
+            "#,
+        username.as_ref()
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SharedNote {
+            note: Note {
+                id: row.id,
+                user_id: row.user_id,
+                created: row.created,
+                title: row.title,
+                content: row.content,
+                updated: row.updated,
+                updated_by: row.updated_by,
+                notebook_id: row.notebook_id,
+                pinned: row.pinned,
+                favorite: row.favorite,
+                archived: row.archived,
+                due_at: row.due_at,
+                remind_at: row.remind_at,
+                reminded: row.reminded,
+            },
+            owner: row.owner,
+            permission: row.permission,
+        })
+        .collect())
+}
+
+pub async fn public_links(note_id: i32, conn: &DbConn) -> Result<Vec<PublicLink>, Error> {
+    sqlx::query_as!(
+        PublicLink,
+        r#"
+SELECT id, note_id, token, created, expires, password IS NOT NULL as "protected!", views
+FROM public_links
+WHERE note_id = $1
+ORDER BY id
+            "#,
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_public_link(id: i32, conn: &DbConn) -> Result<PublicLink, Error> {
+    sqlx::query_as!(
+        PublicLink,
+        r#"
+SELECT id, note_id, token, created, expires, password IS NOT NULL as "protected!", views
+FROM public_links
+WHERE id = $1
+            "#,
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Saves a link to the note with `token`, `password_hash` protects the link if set.
+pub async fn save_public_link(
+    note_id: i32,
+    token: &str,
+    expires: Option<chrono::DateTime<chrono::Utc>>,
+    password_hash: Option<&str>,
+    conn: &DbConn,
+) -> Result<PublicLink, Error> {
+    sqlx::query_as!(
+        PublicLink,
+        r#"
+INSERT INTO public_links ( note_id, token, created, expires, password )
+VALUES ( $1, $2, $3, $4, $5 )
+RETURNING id, note_id, token, created, expires, password IS NOT NULL as "protected!", views
+            "#,
+        note_id,
+        token,
+        chrono::offset::Utc::now(),
+        expires,
+        password_hash
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn delete_public_link(id: i32, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM public_links
+WHERE id = $1
+            ",
+        id
+    )
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Finds the link with `token` unless it expired, returns its id with the password hash.
+pub async fn find_public_link(token: &str, conn: &DbConn) -> Result<(i32, Option<String>), Error> {
+    sqlx::query!(
+        "
+SELECT id, password
+FROM public_links
+WHERE token = $1 AND ( expires IS NULL OR expires > $2 )
+            ",
+        token,
+        chrono::offset::Utc::now()
+    )
+    .fetch_one(conn)
+    .await
+    .map(|row| (row.id, row.password))
+    .map_err(Error::from)
+}
+
+/// Loads the note of the link `link_id` counting it as a view of the link.
+pub async fn view_public_link(link_id: i32, conn: &DbConn) -> Result<PublicNote, Error> {
+    sqlx::query_as!(
+        PublicNote,
+        r#"
+WITH link AS (
+    UPDATE public_links
+    SET views = views + 1
+    WHERE id = $1
+    RETURNING note_id
+)
+SELECT notes.title as "title!", notes.content, notes.created as "created!", notes.updated
+FROM notes
+INNER JOIN link ON link.note_id = notes.id
+            "#,
+        link_id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Loads all notes and tags of the user with links and taggings between them. Archived
+/// notes are left out unless `include_archived` is set.
+pub async fn user_graph<S: AsRef<str>>(
+    username: S,
+    include_archived: bool,
+    conn: &DbConn,
+) -> Result<Graph, Error> {
+    let notes = sqlx::query!(
+        "
+SELECT notes.id, notes.title
+FROM notes
+INNER JOIN users ON users.id = notes.user_id
+WHERE users.username = $1 AND ( $2 OR notes.archived IS NULL )
+ORDER BY notes.id
+            ",
+        username.as_ref(),
+        include_archived
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let tags = sqlx::query!(
+        "
+SELECT tags.id, tags.name, tags.color
+FROM tags
+INNER JOIN users ON users.id = tags.user_id
+WHERE users.username = $1
+ORDER BY tags.name
+            ",
+        username.as_ref()
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let links = sqlx::query!(
+        "
+SELECT note_links.source_id, note_links.target_id
+FROM note_links
+INNER JOIN notes AS source ON source.id = note_links.source_id
+INNER JOIN notes AS target ON target.id = note_links.target_id
+INNER JOIN users ON users.id = source.user_id
+WHERE users.username = $1
+    AND ( $2 OR ( source.archived IS NULL AND target.archived IS NULL ) )
+            ",
+        username.as_ref(),
+        include_archived
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let taggings = sqlx::query!(
+        "
+SELECT notes_tags.note_id, notes_tags.tag_id
+FROM notes_tags
+INNER JOIN notes ON notes.id = notes_tags.note_id
+INNER JOIN users ON users.id = notes.user_id
+WHERE users.username = $1 AND ( $2 OR notes.archived IS NULL )
+            ",
+        username.as_ref(),
+        include_archived
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let nodes = notes
+        .into_iter()
+        .map(|note| GraphNode::Note {
+            id: note.id,
+            title: note.title,
+        })
+        .chain(tags.into_iter().map(|tag| GraphNode::Tag {
+            id: tag.id,
+            name: tag.name,
+            color: tag.color,
+        }))
+        .collect();
+    let edges = links
+        .into_iter()
+        .map(|link| GraphEdge::Link {
+            source: link.source_id,
+            target: link.target_id,
+        })
+        .chain(taggings.into_iter().map(|tagging| GraphEdge::Tagging {
+            note_id: tagging.note_id,
+            tag_id: tagging.tag_id,
+        }))
+        .collect();
+
+    Ok(Graph { nodes, edges })
+}
+
+pub async fn user_tags<S: AsRef<str>>(
+    query: &TagQuery,
+    username: S,
+    conn: &DbConn,
+) -> Result<Vec<TagWithCount>, Error> {
+    let limit = query.limit.unwrap_or(i64::MAX);
+    let sort = query.sort.unwrap_or_default();
+    let ascending = query.order.unwrap_or_default() == SortOrder::Asc;
+
+    let tags = sqlx::query!(
+        r#"
+SELECT
+    tags.id, tags.user_id, tags.name, tags.color, tags.description,
+    COUNT(notes_tags.note_id) as "note_count!"
+FROM tags
+INNER JOIN users on users.id = tags.user_id
+LEFT JOIN notes_tags on notes_tags.tag_id = tags.id
+WHERE users.username = $1
+GROUP BY tags.id
+ORDER BY
+    CASE WHEN $2 = 'usage' AND $3 THEN COUNT(notes_tags.note_id) END ASC,
+    CASE WHEN $2 = 'usage' AND NOT $3 THEN COUNT(notes_tags.note_id) END DESC,
+    CASE WHEN $3 THEN tags.name END ASC,
+    CASE WHEN NOT $3 THEN tags.name END DESC
+LIMIT $4
+            "#,
+        username.as_ref(),
+        sort.as_ref(),
+        ascending,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(tags
+        .into_iter()
+        .map(|record| TagWithCount {
+            tag: Tag {
+                id: record.id,
+                user_id: record.user_id,
+                name: record.name,
+                color: record.color,
+                description: record.description,
+            },
+            note_count: record.note_count,
+        })
+        .collect())
+}
+
+pub async fn load_tag(id: i32, conn: &DbConn) -> Result<Tag, Error> {
+    sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE id = $1
+            "#,
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Re-points all links of the tag `from` to the tag `to`, skipping notes that already
+/// have `to`.
+async fn relink_tag(from: i32, to: i32, tx: &mut DbTx<'_>) -> Result<(), Error> {
+    sqlx::query!(
+        "
+INSERT INTO notes_tags ( note_id, tag_id )
+SELECT DISTINCT note_id, $2::INT
+FROM notes_tags AS source
+WHERE source.tag_id = $1 AND NOT EXISTS (
+    SELECT 1 FROM notes_tags WHERE note_id = source.note_id AND tag_id = $2
+)
+            ",
+        from,
+        to
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+DELETE FROM notes_tags
+WHERE tag_id = $1
+            ",
+        from
+    )
+    .execute(&mut *tx)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+/// Moves every note tagged with `source` to `target` and removes `source`. Tags nested
+/// in `source` are moved under `target`, merging with the ones that already exist there.
+pub async fn merge_tags(source_id: i32, target_id: i32, conn: &DbConn) -> Result<Tag, Error> {
+    let mut tx = conn.begin().await?;
+
+    let source = sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE id = $1
+FOR UPDATE
+            "#,
+        source_id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+    let target = sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE id = $1
+FOR UPDATE
+            "#,
+        target_id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    if tag_path_prefixes(&target.name).contains(&source.name.as_str()) {
+        return Err(Error::InvalidTag(
+            "tag can't be merged into itself or a nested tag".into(),
+        ));
+    }
+
+    let subtree = sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE user_id = $1 AND ( id = $2 OR starts_with(name, $3 || '/') )
+ORDER BY name
+FOR UPDATE
+            "#,
+        source.user_id,
+        source.id,
+        source.name
+    )
+    .fetch_all(&mut tx)
+    .await?;
+
+    for tag in subtree {
+        let name = format!("{}{}", target.name, &tag.name[source.name.len()..]);
+        let existing = sqlx::query!(
+            "
+SELECT id
+FROM tags
+WHERE user_id = $1 AND name = $2
+            ",
+            tag.user_id,
+            name
+        )
+        .fetch_optional(&mut tx)
+        .await?;
+
+        match existing {
+            Some(existing) => {
+                relink_tag(tag.id, existing.id, &mut tx).await?;
+                sqlx::query!(
+                    "
+DELETE FROM tags
+WHERE id = $1
+            ",
+                    tag.id
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "
+UPDATE tags
+SET name = $1
+WHERE id = $2
+            ",
+                    name,
+                    tag.id
+                )
+                .execute(&mut tx)
+                .await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(target)
+}
+
+/// Deletes the tag together with its nested tags, the same way renaming a tag moves them
+/// along.
+pub async fn delete_tag(id: i32, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM tags
+USING tags AS deleted
+WHERE deleted.id = $1
+    AND tags.user_id = deleted.user_id
+    AND ( tags.id = deleted.id OR starts_with(tags.name, deleted.name || '/') )
+            ",
+        id
+    )
+    .execute(conn)
+    .await
+    .map_err(Error::from)
+    .map(|_| ())
+}
+
+/// Returns the id of the tag at the normalized `path` owned by `user_id`, creating it
+/// together with all of its missing ancestors.
+async fn create_tag_path(user_id: i32, path: &str, tx: &mut DbTx<'_>) -> Result<i32, Error> {
+    let mut id = None;
+    for prefix in tag_path_prefixes(path) {
+        let record = sqlx::query!(
+            r#"
+WITH existing AS (
+    SELECT id FROM tags WHERE name = $1 AND user_id = $2
+), created AS (
+    INSERT INTO tags ( name, user_id )
+    SELECT $1, $2
+    WHERE NOT EXISTS ( SELECT 1 FROM existing )
+    RETURNING id
+)
+SELECT id as "id!" FROM existing
+UNION ALL
+SELECT id FROM created
+            "#,
+            prefix,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        id = Some(record.id);
+    }
+
+    id.ok_or_else(|| Error::InvalidTag("empty path".into()))
+}
+
+/// Returns the id of the tag at the normalized `path` owned by `owner`, creating it
+/// together with all of its missing ancestors.
+pub async fn find_or_create_tag<S: AsRef<str>>(
+    path: &str,
+    owner: S,
+    conn: &DbConn,
+) -> Result<i32, Error> {
+    let mut tx = conn.begin().await?;
+
+    let user_id = sqlx::query!(
+        "
+SELECT id
+FROM users
+WHERE username = $1
+            ",
+        owner.as_ref()
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .id;
+    let id = create_tag_path(user_id, path, &mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(id)
+}
+
+/// Applies `patch` to the tag. A new name has to be normalized already, renaming moves
+/// all nested tags along and creates missing ancestors of the new path.
+pub async fn update_tag(id: i32, patch: &TagPatch, conn: &DbConn) -> Result<Tag, Error> {
+    let mut tx = conn.begin().await?;
+
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE id = $1
+FOR UPDATE
+            "#,
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    if let Some(Some(path)) = &patch.name {
+        if let Some((parent, _)) = path.rsplit_once(TAG_SEPARATOR) {
+            if tag_path_prefixes(parent).contains(&tag.name.as_str()) {
+                return Err(Error::InvalidTag("tag can't be moved under itself".into()));
+            }
+            create_tag_path(tag.user_id, parent, &mut tx).await?;
+        }
+
+        sqlx::query!(
+            "
+UPDATE tags
+SET name = $1 || substr(name, length($2) + 1)
+WHERE user_id = $3 AND ( id = $4 OR starts_with(name, $2 || '/') )
+            ",
+            path,
+            tag.name,
+            tag.user_id,
+            id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    let color = patch.color.clone().unwrap_or(tag.color);
+    let description = patch.description.clone().unwrap_or(tag.description);
+    sqlx::query!(
+        "
+UPDATE tags
+SET ( color, description ) = ( $1, $2 )
+WHERE id = $3
+            ",
+        color,
+        description,
+        id
+    )
+    .execute(&mut tx)
+    .await?;
+
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+SELECT id, name, user_id as "user_id: _", color, description
+FROM tags
+WHERE id = $1
+            "#,
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(tag)
+}
+
+pub async fn user_notebooks<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<Vec<Notebook>, Error> {
+    sqlx::query_as!(
+        Notebook,
+        r#"
+SELECT notebooks.id, users.id as "user_id: _", parent_id, notebooks.created, name
+FROM notebooks
+INNER JOIN users on users.id = notebooks.user_id
+WHERE users.username = $1
+ORDER BY notebooks.id
+            "#,
+        username.as_ref()
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_notebook(id: i32, conn: &DbConn) -> Result<Notebook, Error> {
+    sqlx::query_as!(
+        Notebook,
+        "
+SELECT id, user_id, parent_id, created, name
+FROM notebooks
+WHERE id = $1
+            ",
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn save_notebook<S: AsRef<str>>(
+    notebook: &NewNotebook,
+    owner: S,
+    conn: &DbConn,
+) -> Result<Notebook, Error> {
+    sqlx::query_as!(
+        Notebook,
+        "
+INSERT INTO notebooks ( user_id, parent_id, created, name )
+VALUES ( ( SELECT id FROM users WHERE username = $1 ), $2, $3, $4 )
+RETURNING id, user_id, parent_id, created, name
+            ",
+        owner.as_ref(),
+        notebook.parent_id,
+        chrono::offset::Utc::now(),
+        notebook.name,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Renames the notebook and moves it under `notebook.parent_id`. Fails if the new
+/// parent is the notebook itself or one of its descendants.
+pub async fn update_notebook(
+    id: i32,
+    notebook: &NewNotebook,
+    conn: &DbConn,
+) -> Result<Notebook, Error> {
+    let mut tx = conn.begin().await?;
+
+    if let Some(parent_id) = notebook.parent_id {
+        let cycle = sqlx::query!(
+            r#"
+WITH RECURSIVE subtree AS (
+    SELECT id FROM notebooks WHERE id = $1
+    UNION
+    SELECT notebooks.id FROM notebooks
+    INNER JOIN subtree ON notebooks.parent_id = subtree.id
+)
+SELECT EXISTS ( SELECT 1 FROM subtree WHERE id = $2 ) as "cycle!"
+            "#,
+            id,
+            parent_id
+        )
+        .fetch_one(&mut tx)
+        .await?
+        .cycle;
+
+        if cycle {
+            return Err(Error::InvalidNotebook(
+                "notebook can't be moved into itself".into(),
+            ));
+        }
+    }
+
+    let notebook = sqlx::query_as!(
+        Notebook,
+        "
+UPDATE notebooks
+SET ( parent_id, name ) = ( $1, $2 )
+WHERE id = $3
+RETURNING id, user_id, parent_id, created, name
+            ",
+        notebook.parent_id,
+        notebook.name,
+        id
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(notebook)
+}
+
+/// Deletes the notebook together with all nested notebooks, notes filed in them are
+/// kept outside of any notebook.
+pub async fn delete_notebook(id: i32, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM notebooks
+WHERE id = $1
+            ",
+        id
+    )
+    .execute(conn)
+    .await
+    .map_err(Error::from)
+    .map(|_| ())
+}
+
+pub async fn user_saved_searches<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<Vec<SavedSearch>, Error> {
+    sqlx::query_as!(
+        SavedSearch,
+        r#"
+SELECT saved_searches.id, users.id as "user_id: _", saved_searches.created, name, query
+FROM saved_searches
+INNER JOIN users on users.id = saved_searches.user_id
+WHERE users.username = $1
+ORDER BY name
+            "#,
+        username.as_ref()
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_saved_search(id: i32, conn: &DbConn) -> Result<SavedSearch, Error> {
+    sqlx::query_as!(
+        SavedSearch,
+        "
+SELECT id, user_id, created, name, query
+FROM saved_searches
+WHERE id = $1
+            ",
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn save_saved_search<S: AsRef<str>>(
+    name: &str,
+    query: &serde_json::Value,
+    owner: S,
+    conn: &DbConn,
+) -> Result<SavedSearch, Error> {
+    sqlx::query_as!(
+        SavedSearch,
+        "
+INSERT INTO saved_searches ( user_id, created, name, query )
+VALUES ( ( SELECT id FROM users WHERE username = $1 ), $2, $3, $4 )
+RETURNING id, user_id, created, name, query
+            ",
+        owner.as_ref(),
+        chrono::offset::Utc::now(),
+        name,
+        query
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn update_saved_search(
+    id: i32,
+    name: &str,
+    query: &serde_json::Value,
+    conn: &DbConn,
+) -> Result<SavedSearch, Error> {
+    sqlx::query_as!(
+        SavedSearch,
+        "
+UPDATE saved_searches
+SET ( name, query ) = ( $1, $2 )
+WHERE id = $3
+RETURNING id, user_id, created, name, query
+            ",
+        name,
+        query,
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn delete_saved_search(id: i32, conn: &DbConn) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM saved_searches
+WHERE id = $1
+            ",
+        id
+    )
+    .execute(conn)
+    .await
+    .map_err(Error::from)
+    .map(|_| ())
+}
+
+pub async fn user_webhooks<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<Vec<Webhook>, Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+SELECT webhooks.id, webhooks.user_id, url, secret, events, tag, webhooks.created
+FROM webhooks
+INNER JOIN users on users.id = webhooks.user_id
+WHERE users.username = $1
+ORDER BY webhooks.id
+            "#,
+        username.as_ref()
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_webhook(id: i32, conn: &DbConn) -> Result<Webhook, Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+SELECT id, user_id, url, secret, events, tag, created
+FROM webhooks
+WHERE id = $1
+            "#,
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn save_webhook<S: AsRef<str>>(
+    webhook: &NewWebhook,
+    secret: &str,
+    username: S,
+    conn: &DbConn,
+) -> Result<Webhook, Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+INSERT INTO webhooks ( user_id, url, secret, events, tag, created )
+SELECT users.id, $2, $3, $4, $5, $6
+FROM users
+WHERE users.username = $1
+RETURNING id, user_id, url, secret, events, tag, created
+            "#,
+        username.as_ref(),
+        webhook.url,
+        secret,
+        &webhook.events,
+        webhook.tag,
+        chrono::offset::Utc::now(),
     )
     .fetch_one(conn)
     .await
     .map_err(Error::from)
 }
 
-pub async fn delete_note(id: i32, conn: &DbConn) -> Result<(), Error> {
-    clear_note_tags(id, &conn).await?;
-    sqlx::query!(
-        "
-DELETE FROM notes
+/// Changes what and where the webhook delivers, the secret stays the same.
+pub async fn update_webhook(
+    id: i32,
+    webhook: &NewWebhook,
+    conn: &DbConn,
+) -> Result<Webhook, Error> {
+    sqlx::query_as!(
+        Webhook,
+        r#"
+UPDATE webhooks
+SET url = $2, events = $3, tag = $4
 WHERE id = $1
-            ",
-        id
+RETURNING id, user_id, url, secret, events, tag, created
+            "#,
+        id,
+        webhook.url,
+        &webhook.events,
+        webhook.tag,
     )
-    .execute(conn)
+    .fetch_one(conn)
     .await
-    .map(|_| ())
     .map_err(Error::from)
 }
 
-pub async fn clear_note_tags(id: i32, conn: &DbConn) -> Result<(), Error> {
+pub async fn delete_webhook(id: i32, conn: &DbConn) -> Result<(), Error> {
     sqlx::query!(
         "
-DELETE FROM notes_tags
-WHERE note_id = $1
+DELETE FROM webhooks
+WHERE id = $1
             ",
         id
     )
     .execute(conn)
     .await
-    .map(|_| ())
     .map_err(Error::from)
+    .map(|_| ())
 }
 
-pub async fn update_note(id: i32, new_note: &NewNote, conn: &DbConn) -> Result<(), Error> {
-    sqlx::query!(
-        "
-UPDATE notes
-SET ( title, content ) = ( $1, $2 )
-WHERE id = $3
-            ",
-        new_note.title,
-        new_note.content,
-        id
+/// Loads the latest `limit` deliveries of the webhook, newest first.
+pub async fn webhook_deliveries(
+    webhook_id: i32,
+    limit: i64,
+    conn: &DbConn,
+) -> Result<Vec<WebhookDelivery>, Error> {
+    sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+SELECT
+    id, webhook_id, event, payload, status as "status: _", attempts, response_status, error,
+    created, next_attempt, delivered
+FROM webhook_deliveries
+WHERE webhook_id = $1
+ORDER BY created DESC, id DESC
+LIMIT $2
+            "#,
+        webhook_id,
+        limit
     )
-    .execute(conn)
+    .fetch_all(conn)
     .await
-    .map(|_| ())
     .map_err(Error::from)
 }
 
-pub async fn tag_note(note_id: i32, tag_id: i32, conn: &DbConn) -> Result<(), Error> {
-    sqlx::query!(
-        "
-INSERT INTO notes_tags ( note_id, tag_id )
-VALUES ( $1, $2 )
-            ",
-        note_id,
-        tag_id
-    )
-    .execute(conn)
-    .await
-    .map(|_| ())
-    .map_err(Error::from)
+/// Delivery claimed for an attempt along with where to send it.
+pub struct ClaimedDelivery {
+    pub id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
 }
 
-pub async fn untag_note(note_id: i32, tag_id: i32, conn: &DbConn) -> Result<(), Error> {
-    sqlx::query!(
+/// Queues a delivery of `payload` to the webhook, it isn't attempted by the background
+/// delivery before `claimed_until` so that the caller can attempt it right away.
+pub async fn queue_webhook_delivery(
+    webhook: &Webhook,
+    event: &str,
+    payload: &serde_json::Value,
+    claimed_until: chrono::DateTime<chrono::Utc>,
+    conn: &DbConn,
+) -> Result<ClaimedDelivery, Error> {
+    let id = sqlx::query!(
         "
-DELETE FROM notes_tags
-WHERE note_id = $1 AND tag_id = $2
+INSERT INTO webhook_deliveries ( webhook_id, event, payload, created, next_attempt )
+VALUES ( $1, $2, $3, $4, $5 )
+RETURNING id
             ",
-        note_id,
-        tag_id
+        webhook.id,
+        event,
+        payload,
+        chrono::offset::Utc::now(),
+        claimed_until
     )
-    .execute(conn)
+    .fetch_one(conn)
     .await
-    .map(|_| ())
-    .map_err(Error::from)
+    .map_err(Error::from)?
+    .id;
+
+    Ok(ClaimedDelivery {
+        id,
+        event: event.to_string(),
+        payload: payload.clone(),
+        attempts: 0,
+        url: webhook.url.clone(),
+        secret: webhook.secret.clone(),
+    })
 }
 
-pub async fn note_tags(note_id: i32, conn: &DbConn) -> Result<Vec<Tag>, Error> {
+/// Claims up to `limit` pending deliveries that are due until `claimed_until`. Deliveries
+/// claimed by one server instance are skipped by the others.
+pub async fn claim_webhook_deliveries(
+    limit: i64,
+    claimed_until: chrono::DateTime<chrono::Utc>,
+    conn: &DbConn,
+) -> Result<Vec<ClaimedDelivery>, Error> {
     sqlx::query_as!(
-        Tag,
+        ClaimedDelivery,
         r#"
-SELECT tags.id, u.id as "user_id: _", name
-FROM tags
-INNER JOIN notes_tags AS nt ON nt.tag_id = tags.id
-INNER JOIN users AS u ON u.id = tags.user_id
-WHERE nt.note_id = $1
+WITH due AS (
+    SELECT id
+    FROM webhook_deliveries
+    WHERE status = 'pending' AND next_attempt <= $2
+    ORDER BY next_attempt
+    LIMIT $1
+    FOR UPDATE SKIP LOCKED
+)
+UPDATE webhook_deliveries
+SET next_attempt = $3
+FROM due, webhooks
+WHERE webhook_deliveries.id = due.id AND webhooks.id = webhook_deliveries.webhook_id
+RETURNING
+    webhook_deliveries.id, webhook_deliveries.event, webhook_deliveries.payload,
+    webhook_deliveries.attempts, webhooks.url, webhooks.secret
             "#,
-        note_id
+        limit,
+        chrono::offset::Utc::now(),
+        claimed_until
     )
     .fetch_all(conn)
     .await
     .map_err(Error::from)
 }
 
-pub async fn user_tags<S: AsRef<str>>(
-    filter: QueryFilter,
-    username: S,
+/// Records an attempt of the delivery, it's retried at `next_attempt` while still pending.
+pub async fn finish_webhook_delivery(
+    id: i32,
+    status: DeliveryStatus,
+    response_status: Option<i32>,
+    error: Option<&str>,
+    next_attempt: Option<chrono::DateTime<chrono::Utc>>,
     conn: &DbConn,
-) -> Result<Vec<Tag>, Error> {
-    let limit = if let Some(l) = filter.limit {
-        l
+) -> Result<WebhookDelivery, Error> {
+    let delivered = if status == DeliveryStatus::Succeeded {
+        Some(chrono::offset::Utc::now())
     } else {
-        i64::MAX
+        None
     };
 
     sqlx::query_as!(
-        Tag,
+        WebhookDelivery,
         r#"
-SELECT tags.id, users.id as "user_id: _", name
-FROM tags
-INNER JOIN users on users.id = tags.user_id
-WHERE users.username = $1
-LIMIT $2
+UPDATE webhook_deliveries
+SET
+    status = $2, attempts = attempts + 1, response_status = $3, error = $4, next_attempt = $5,
+    delivered = $6
+WHERE id = $1
+RETURNING
+    id, webhook_id, event, payload, status as "status: _", attempts, response_status, error,
+    created, next_attempt, delivered
             "#,
-        username.as_ref(),
-        limit
+        id,
+        status as _,
+        response_status,
+        error,
+        next_attempt,
+        delivered
     )
-    .fetch_all(conn)
+    .fetch_one(conn)
     .await
     .map_err(Error::from)
 }
 
-pub async fn load_tag(id: i32, conn: &DbConn) -> Result<Tag, Error> {
+/// Loads pending reminders and upcoming due dates of active notes of the user, the
+/// earliest first. With `before` only the ones due before then are loaded.
+pub async fn upcoming_reminders<S: AsRef<str>>(
+    username: S,
+    before: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<i64>,
+    conn: &DbConn,
+) -> Result<Vec<Reminder>, Error> {
     sqlx::query_as!(
-        Tag,
+        Reminder,
         r#"
-SELECT id, name, user_id as "user_id: _"
-FROM tags
-WHERE id = $1
+WITH upcoming AS (
+    SELECT
+        notes.id, notes.title, notes.due_at,
+        CASE WHEN notes.reminded IS NULL THEN notes.remind_at END AS remind_at
+    FROM notes
+    INNER JOIN users on users.id = notes.user_id
+    WHERE users.username = $1 AND notes.archived IS NULL
+)
+SELECT id as "note_id!", title as "note_title!", due_at, remind_at
+FROM upcoming
+WHERE ( remind_at IS NOT NULL OR due_at >= $2 )
+    AND ( $3::TIMESTAMPTZ IS NULL OR LEAST(remind_at, due_at) < $3 )
+ORDER BY LEAST(remind_at, due_at), id
+LIMIT $4
             "#,
-        id
+        username.as_ref(),
+        chrono::offset::Utc::now(),
+        before,
+        limit.unwrap_or(i64::MAX)
     )
-    .fetch_one(conn)
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Postpones the reminder of the note to `remind_at`, a sent reminder becomes pending
+/// again.
+pub async fn snooze_reminder(
+    note_id: i32,
+    remind_at: chrono::DateTime<chrono::Utc>,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "
+UPDATE notes
+SET remind_at = $1, reminded = NULL
+WHERE id = $2
+            ",
+        remind_at,
+        note_id
+    )
+    .execute(conn)
     .await
+    .map(|_| ())
     .map_err(Error::from)
 }
 
-pub async fn save_tag(tag: &NewTag, conn: &DbConn) -> Result<Tag, Error> {
+/// Reminder claimed to be sent along with who to send it to.
+pub struct DueReminder {
+    pub note_id: i32,
+    pub note_title: String,
+    pub due_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub remind_at: chrono::DateTime<chrono::Utc>,
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    /// Time zone from the settings of the owner
+    pub timezone: String,
+}
+
+/// Claims up to `limit` reminders that are due by marking them as sent. Reminders
+/// claimed by one server instance are skipped by the others.
+pub async fn claim_due_reminders(limit: i64, conn: &DbConn) -> Result<Vec<DueReminder>, Error> {
     sqlx::query_as!(
-        Tag,
+        DueReminder,
         r#"
-INSERT INTO tags ( name, user_id )
-VALUES ( $1, (SELECT id FROM users WHERE username = $2) )
-RETURNING id, name, user_id as "user_id: _" 
+WITH due AS (
+    SELECT id
+    FROM notes
+    WHERE remind_at <= $2 AND reminded IS NULL AND archived IS NULL
+    ORDER BY remind_at
+    LIMIT $1
+    FOR UPDATE SKIP LOCKED
+)
+UPDATE notes
+SET reminded = $2
+FROM due, users
+LEFT JOIN user_settings ON user_settings.user_id = users.id
+WHERE notes.id = due.id AND users.id = notes.user_id
+RETURNING
+    notes.id as note_id, notes.title as note_title, notes.due_at,
+    notes.remind_at as "remind_at!", users.id as user_id, users.username, users.email,
+    COALESCE(user_settings.timezone, 'UTC') as "timezone!"
             "#,
-        tag.name,
-        tag.username,
+        limit,
+        chrono::offset::Utc::now()
     )
-    .fetch_one(conn)
+    .fetch_all(conn)
     .await
     .map_err(Error::from)
 }
 
-pub async fn delete_tag(id: i32, conn: &DbConn) -> Result<(), Error> {
+/// Queues deliveries of the reminder to webhooks of the owner subscribed to
+/// `note.reminder`, webhooks limited to a tag only get reminders of notes tagged with it.
+pub async fn queue_reminder_deliveries(
+    reminder: &DueReminder,
+    payload: &serde_json::Value,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    let now = chrono::offset::Utc::now();
     sqlx::query!(
         "
-DELETE FROM tags
-WHERE id = $1
+INSERT INTO webhook_deliveries ( webhook_id, event, payload, created, next_attempt )
+SELECT webhooks.id, $3::TEXT, $4, $5, $5
+FROM webhooks
+WHERE webhooks.user_id = $1
+    AND $3::TEXT = ANY(webhooks.events)
+    AND ( webhooks.tag IS NULL OR EXISTS (
+        SELECT 1
+        FROM notes_tags
+        INNER JOIN tags ON tags.id = notes_tags.tag_id
+        WHERE notes_tags.note_id = $2 AND tags.name = webhooks.tag
+    ))
             ",
-        id
+        reminder.user_id,
+        reminder.note_id,
+        REMINDER_EVENT,
+        payload,
+        now
     )
     .execute(conn)
     .await
-    .map_err(Error::from)
     .map(|_| ())
+    .map_err(Error::from)
+}
+
+pub async fn calendar_feed<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<CalendarFeed, Error> {
+    sqlx::query_as!(
+        CalendarFeed,
+        "
+SELECT calendar_feeds.user_id, token, calendar_feeds.created
+FROM calendar_feeds
+INNER JOIN users on users.id = calendar_feeds.user_id
+WHERE users.username = $1
+            ",
+        username.as_ref()
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
 }
 
-pub async fn search_tag<S: AsRef<str>>(
-    tag: S,
+/// Creates the calendar feed of the user or replaces the token of the existing one, the
+/// old address stops working.
+pub async fn save_calendar_feed<S: AsRef<str>>(
     username: S,
+    token: &str,
     conn: &DbConn,
-) -> Result<Option<i32>, Error> {
+) -> Result<CalendarFeed, Error> {
+    sqlx::query_as!(
+        CalendarFeed,
+        "
+INSERT INTO calendar_feeds ( user_id, token, created )
+SELECT id, $2, $3
+FROM users
+WHERE username = $1
+ON CONFLICT ( user_id ) DO UPDATE
+SET token = EXCLUDED.token, created = EXCLUDED.created
+RETURNING user_id, token, created
+            ",
+        username.as_ref(),
+        token,
+        chrono::offset::Utc::now()
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn delete_calendar_feed<S: AsRef<str>>(username: S, conn: &DbConn) -> Result<(), Error> {
     sqlx::query!(
         "
-SELECT tags.id
-FROM tags
-INNER JOIN users ON users.id = tags.user_id
-WHERE name = $1 AND username = $2
+DELETE FROM calendar_feeds
+USING users
+WHERE users.id = calendar_feeds.user_id AND users.username = $1
             ",
-        tag.as_ref(),
         username.as_ref()
     )
-    .fetch_optional(conn)
+    .execute(conn)
+    .await
+    .map(|_| ())
+    .map_err(Error::from)
+}
+
+pub async fn find_calendar_feed(token: &str, conn: &DbConn) -> Result<CalendarFeed, Error> {
+    sqlx::query_as!(
+        CalendarFeed,
+        "
+SELECT user_id, token, created
+FROM calendar_feeds
+WHERE token = $1
+            ",
+        token
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Loads active notes of the user that have a due date along with their tags and how
+/// many of their tasks are done, the earliest due first.
+pub async fn calendar_entries(user_id: i32, conn: &DbConn) -> Result<Vec<CalendarEntry>, Error> {
+    sqlx::query_as!(
+        CalendarEntry,
+        r#"
+SELECT
+    notes.id as note_id, notes.title, notes.created, notes.updated, notes.due_at as "due_at!",
+    CASE WHEN notes.reminded IS NULL THEN notes.remind_at END as remind_at,
+    ARRAY(
+        SELECT tags.name
+        FROM notes_tags
+        INNER JOIN tags ON tags.id = notes_tags.tag_id
+        WHERE notes_tags.note_id = notes.id
+        ORDER BY tags.name
+    ) as "tags!: Vec<String>",
+    ( SELECT count(*) FROM note_tasks WHERE note_id = notes.id ) as "tasks!",
+    ( SELECT count(*) FROM note_tasks WHERE note_id = notes.id AND done ) as "tasks_done!"
+FROM notes
+WHERE notes.user_id = $1 AND notes.due_at IS NOT NULL AND notes.archived IS NULL
+ORDER BY notes.due_at, notes.id
+            "#,
+        user_id
+    )
+    .fetch_all(conn)
     .await
     .map_err(Error::from)
-    .map(|maybe| maybe.map(|record| record.id))
 }
 
 pub async fn load_user<S: AsRef<str>>(username: S, conn: &DbConn) -> Result<User, Error> {
@@ -402,3 +2421,180 @@ WHERE sub = $1
     .map_err(Error::from)
     .map(|_| ())
 }
+
+pub async fn load_user_settings<S: AsRef<str>>(
+    username: S,
+    conn: &DbConn,
+) -> Result<UserSettings, Error> {
+    sqlx::query_as!(
+        UserSettings,
+        r#"
+SELECT
+    users.id as "user_id!",
+    COALESCE(s.theme, 'auto') as "theme!: _",
+    s.language as "language?",
+    COALESCE(s.timezone, 'UTC') as "timezone!"
+FROM users
+LEFT JOIN user_settings AS s ON s.user_id = users.id
+WHERE users.username = $1
+            "#,
+        username.as_ref()
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn save_user_settings<S: AsRef<str>>(
+    username: S,
+    settings: &NewUserSettings,
+    conn: &DbConn,
+) -> Result<UserSettings, Error> {
+    sqlx::query_as!(
+        UserSettings,
+        r#"
+INSERT INTO user_settings ( user_id, theme, language, timezone )
+VALUES ( ( SELECT id FROM users WHERE username = $1 ), $2, $3, $4 )
+ON CONFLICT ( user_id ) DO UPDATE
+SET ( theme, language, timezone ) = ( EXCLUDED.theme, EXCLUDED.language, EXCLUDED.timezone )
+RETURNING user_id, theme as "theme: _", language, timezone
+            "#,
+        username.as_ref(),
+        settings.theme as _,
+        settings.language,
+        settings.timezone,
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn note_attachments(note_id: i32, conn: &DbConn) -> Result<Vec<Attachment>, Error> {
+    sqlx::query_as!(
+        Attachment,
+        "
+SELECT *
+FROM attachments
+WHERE note_id = $1
+ORDER BY id
+            ",
+        note_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(Error::from)
+}
+
+pub async fn load_attachment(id: i32, conn: &DbConn) -> Result<Attachment, Error> {
+    sqlx::query_as!(
+        Attachment,
+        "
+SELECT *
+FROM attachments
+WHERE id = $1
+            ",
+        id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(Error::from)
+}
+
+/// Stores `data` as an attachment of `note` as long as it fits in the quota of the owner.
+/// The attachment row reserves the quota before its content is uploaded to `blobs`, so
+/// the owner isn't locked during the upload. A failed upload removes the row again.
+pub async fn save_attachment(
+    note: &Note,
+    filename: &str,
+    content_type: &str,
+    data: bytes::Bytes,
+    blobs: &Blobs,
+    conn: &DbConn,
+) -> Result<Attachment, Error> {
+    let mut tx = conn.begin().await?;
+
+    // locking the owner serializes concurrent uploads checking the same quota
+    let quota = sqlx::query!(
+        "
+SELECT attachment_quota
+FROM users
+WHERE id = $1
+FOR UPDATE
+            ",
+        note.user_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .attachment_quota;
+
+    let used = sqlx::query!(
+        r#"
+SELECT COALESCE(SUM(size), 0)::BIGINT as "used!"
+FROM attachments
+WHERE user_id = $1
+            "#,
+        note.user_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .used;
+
+    let size = data.len() as i64;
+    if used + size > quota {
+        return Err(Error::QuotaExceeded { used, quota });
+    }
+
+    let attachment = sqlx::query_as!(
+        Attachment,
+        "
+INSERT INTO attachments ( note_id, user_id, created, filename, content_type, size )
+VALUES ( $1, $2, $3, $4, $5, $6 )
+RETURNING *
+            ",
+        note.id,
+        note.user_id,
+        chrono::offset::Utc::now(),
+        filename,
+        content_type,
+        size
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    tx.commit().await?;
+
+    if let Err(err) = blobs.put(&attachment.blob_key(), data, content_type).await {
+        delete_attachment(&attachment, blobs, conn).await?;
+        return Err(err);
+    }
+
+    Ok(attachment)
+}
+
+pub async fn delete_attachment(
+    attachment: &Attachment,
+    blobs: &Blobs,
+    conn: &DbConn,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "
+DELETE FROM attachments
+WHERE id = $1
+            ",
+        attachment.id
+    )
+    .execute(conn)
+    .await?;
+
+    delete_blob(attachment, blobs).await;
+
+    Ok(())
+}
+
+/// Removes the content of an attachment that is already gone from the database. A
+/// failure leaves an orphaned blob behind which is only logged.
+async fn delete_blob(attachment: &Attachment, blobs: &Blobs) {
+    if let Err(err) = blobs.delete(&attachment.blob_key()).await {
+        log::warn!("failed to delete attachment {} - {}", attachment.id, err);
+    }
+}
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::env;
+
+use super::Notifier;
+use crate::models::DueReminder;
+use crate::Error;
+
+pub const SMTP_HOST_ENV: &str = "NOTOR_SMTP_HOST";
+/// Defaults to the standard port of the selected `NOTOR_SMTP_TLS` mode
+pub const SMTP_PORT_ENV: &str = "NOTOR_SMTP_PORT";
+/// Either `starttls` (default), `tls` or `none`
+pub const SMTP_TLS_ENV: &str = "NOTOR_SMTP_TLS";
+pub const SMTP_USERNAME_ENV: &str = "NOTOR_SMTP_USERNAME";
+pub const SMTP_PASSWORD_ENV: &str = "NOTOR_SMTP_PASSWORD";
+/// Sender of reminders like `notor <notor@example.com>`
+pub const SMTP_FROM_ENV: &str = "NOTOR_SMTP_FROM";
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+/// Emails reminders to the address of the owner through an SMTP relay.
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+fn required_env(name: &str) -> Result<String, Error> {
+    env::var(name).map_err(|_| Error::NotifierError(format!("`{}` is not set", name)))
+}
+
+fn notifier_error<E: ToString>(err: E) -> Error {
+    Error::NotifierError(err.to_string())
+}
+
+fn format_local(datetime: &DateTime<Utc>, tz: &Tz) -> String {
+    datetime
+        .with_timezone(tz)
+        .format(DATETIME_FORMAT)
+        .to_string()
+}
+
+impl EmailNotifier {
+    /// Reads the relay and sender from `NOTOR_SMTP_*` variables, credentials are only
+    /// used when both the username and password are set.
+    pub fn from_env() -> Result<Self, Error> {
+        let host = required_env(SMTP_HOST_ENV)?;
+        let mut builder = match env::var(SMTP_TLS_ENV).as_deref() {
+            Ok("starttls") | Err(_) => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)
+                .map_err(notifier_error)?,
+            Ok("tls") => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&host).map_err(notifier_error)?
+            }
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok(other) => {
+                return Err(Error::NotifierError(format!(
+                    "unknown SMTP TLS mode `{}`",
+                    other
+                )))
+            }
+        };
+        if let Ok(port) = env::var(SMTP_PORT_ENV) {
+            builder = builder.port(port.parse().map_err(notifier_error)?);
+        }
+        if let (Ok(username), Ok(password)) =
+            (env::var(SMTP_USERNAME_ENV), env::var(SMTP_PASSWORD_ENV))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(EmailNotifier {
+            transport: builder.build(),
+            from: required_env(SMTP_FROM_ENV)?
+                .parse()
+                .map_err(notifier_error)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &'static str {
+        "email"
+    }
+
+    async fn notify(&self, reminder: &DueReminder) -> Result<(), Error> {
+        let tz: Tz = reminder.timezone.parse().unwrap_or(Tz::UTC);
+        let mut body = format!(
+            "Hi {},\n\nthis is your reminder of the note \"{}\" set for {}.\n",
+            reminder.username,
+            reminder.note_title,
+            format_local(&reminder.remind_at, &tz)
+        );
+        if let Some(due_at) = &reminder.due_at {
+            body.push_str(&format!("The note is due {}.\n", format_local(due_at, &tz)));
+        }
+
+        let to = Mailbox::new(
+            Some(reminder.username.clone()),
+            reminder.email.parse().map_err(notifier_error)?,
+        );
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(format!("Reminder: {}", reminder.note_title))
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(notifier_error)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(notifier_error)
+    }
+}
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+
+use super::Notifier;
+use crate::db::Db;
+use crate::events::announce;
+use crate::models::DueReminder;
+use crate::Error;
+use notor_core::models::{Event, EventAction, EventKind};
+
+/// Sends reminders to the event streams of their owners, browsers with notor open show
+/// them right away.
+pub struct EventNotifier {
+    db: Db,
+}
+
+impl EventNotifier {
+    pub fn new(db: Db) -> Self {
+        EventNotifier { db }
+    }
+}
+
+#[async_trait]
+impl Notifier for EventNotifier {
+    fn name(&self) -> &'static str {
+        "event"
+    }
+
+    async fn notify(&self, reminder: &DueReminder) -> Result<(), Error> {
+        let event = Event {
+            kind: EventKind::Note,
+            action: EventAction::Reminder,
+            id: reminder.note_id,
+            user_id: reminder.user_id,
+            tag: None,
+        };
+
+        announce(&event, &self.db).await
+    }
+}
@@ -0,0 +1,53 @@
+mod email;
+mod event;
+mod webhook;
+
+use async_trait::async_trait;
+use std::env;
+use std::sync::Arc;
+
+use crate::db::Db;
+use crate::models::DueReminder;
+use crate::Error;
+pub use email::EmailNotifier;
+pub use event::EventNotifier;
+pub use webhook::WebhookNotifier;
+
+/// Selects the notifiers sending reminders as a comma separated list of `event`,
+/// `webhook` and `email`, `event,webhook` by default
+pub const NOTIFIERS_ENV: &str = "NOTOR_NOTIFIERS";
+const DEFAULT_NOTIFIERS: &str = "event,webhook";
+
+/// Tells the owner of a note that its reminder is due.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+    async fn notify(&self, reminder: &DueReminder) -> Result<(), Error>;
+}
+
+pub type Notifiers = Arc<[Box<dyn Notifier>]>;
+
+/// Creates the notifiers configured in the environment, see [`EmailNotifier::from_env`]
+/// for the settings of email.
+pub fn notifiers(db: &Db) -> Result<Notifiers, Error> {
+    let names = env::var(NOTIFIERS_ENV).unwrap_or_else(|_| DEFAULT_NOTIFIERS.to_string());
+
+    names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| -> Result<Box<dyn Notifier>, Error> {
+            match name {
+                "event" => Ok(Box::new(EventNotifier::new(db.clone()))),
+                "webhook" => Ok(Box::new(WebhookNotifier::new(db.clone()))),
+                "email" => Ok(Box::new(EmailNotifier::from_env()?)),
+                other => Err(Error::NotifierError(format!(
+                    "unknown notifier `{}`",
+                    other
+                ))),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Arc::from)
+}
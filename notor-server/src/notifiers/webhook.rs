@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+use chrono::Utc;
+
+use super::Notifier;
+use crate::db::Db;
+use crate::models::{queue_reminder_deliveries, DueReminder};
+use crate::Error;
+use notor_core::models::REMINDER_EVENT;
+
+/// Sends reminders to webhooks subscribed to `note.reminder`. Deliveries are queued and
+/// retried like those of other events.
+pub struct WebhookNotifier {
+    db: Db,
+}
+
+impl WebhookNotifier {
+    pub fn new(db: Db) -> Self {
+        WebhookNotifier { db }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn notify(&self, reminder: &DueReminder) -> Result<(), Error> {
+        let payload = serde_json::json!({
+            "event": REMINDER_EVENT,
+            "kind": "note",
+            "action": "reminder",
+            "id": reminder.note_id,
+            "user_id": reminder.user_id,
+            "tag": null,
+            "title": reminder.note_title,
+            "due_at": reminder.due_at,
+            "remind_at": reminder.remind_at,
+            "created": Utc::now(),
+        });
+
+        queue_reminder_deliveries(reminder, &payload, &self.db).await
+    }
+}
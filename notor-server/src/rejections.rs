@@ -1,13 +1,24 @@
 use std::convert::Infallible;
 use warp::body::BodyDeserializeError;
 use warp::http::StatusCode;
-use warp::{reject::InvalidHeader, reply, Rejection, Reply};
+use warp::{
+    reject::{InvalidHeader, InvalidQuery},
+    reply, Rejection, Reply,
+};
 
 use crate::Error;
 use notor_core::models::ErrReply;
 
 type Response = Result<warp::reply::Response, Infallible>;
 
+/// SQLSTATE codes of unique and foreign key violations
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+
+fn is_constraint_violation(code: Option<&str>) -> bool {
+    matches!(code, Some(UNIQUE_VIOLATION) | Some(FOREIGN_KEY_VIOLATION))
+}
+
 pub(crate) async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infallible> {
     use sqlx::Error::*;
     use Error::*;
@@ -25,25 +36,45 @@ pub(crate) async fn handle_rejection(err: Rejection) -> Result<impl Reply, Infal
         let (c, m) = match err {
             DbError(inner) => match inner {
                 RowNotFound => (StatusCode::NOT_FOUND, "not found".into()),
+                Database(db_err) if is_constraint_violation(db_err.code().as_deref()) => (
+                    StatusCode::CONFLICT,
+                    match db_err.constraint() {
+                        Some(constraint) => format!("conflicts with `{}`", constraint),
+                        None => db_err.message().to_string(),
+                    },
+                ),
                 // #TODO: handle all
                 _ => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
             },
-            InvalidRole(_) => (StatusCode::BAD_REQUEST, err.to_string()),
+            InvalidRole(_) | InvalidTheme(_) | InvalidLanguage(_) | InvalidTimeZone(_)
+            | InvalidPatch(_) | InvalidNotebook(_) | InvalidTag(_) | InvalidColor(_)
+            | InvalidSearch(_) | InvalidAttachment(_) | InvalidShare(_) | InvalidPublicLink(_)
+            | InvalidEdit(_) | InvalidWebhook(_) | InvalidReminder(_) | InvalidLink(_) => {
+                (StatusCode::BAD_REQUEST, err.to_string())
+            }
+            QuotaExceeded { .. } => (StatusCode::PAYLOAD_TOO_LARGE, err.to_string()),
             TokenVerificationError(_)
             | Utf8ConversionError(_)
             | InvalidTimestamp
             | InvalidHeaderKey(_)
             | BodySerializieError(_)
+            | BlobError(_)
+            | NotifierError(_)
+            | PasswordHashError(_)
             | InvalidHeaderInternalErr(_) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
             AuthHeaderMissing | InvalidAuthHeader | InvalidAuthToken | AuthTokenExpired
             | InvalidPassword => (StatusCode::FORBIDDEN, err.to_string()),
             UnauthorizedAccess => (StatusCode::UNAUTHORIZED, err.to_string()),
+            OwnerMismatch { .. } => (StatusCode::FORBIDDEN, err.to_string()),
         };
         code = c;
         message = m;
     } else if let Some(err) = err.find::<InvalidHeader>() {
         code = StatusCode::BAD_REQUEST;
         message = err.to_string();
+    } else if let Some(err) = err.find::<InvalidQuery>() {
+        code = StatusCode::BAD_REQUEST;
+        message = err.to_string();
     }
 
     Ok(reply::with_status(reply::json(&ErrReply::new(message)), code).into_response()) as Response
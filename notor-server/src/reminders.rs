@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::db::Db;
+use crate::models::{claim_due_reminders, DueReminder};
+use crate::notifiers::Notifiers;
+
+/// Reminders claimed at once by a server instance
+const BATCH_SIZE: i64 = 10;
+/// Time between looking for due reminders
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Starts sending due reminders through all notifiers in the background. Each reminder is
+/// sent once, a notifier failing to send it is only logged.
+pub fn start(db: Db, notifiers: Notifiers) {
+    tokio::spawn(async move {
+        loop {
+            send_due(&db, &notifiers).await;
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+async fn send_due(db: &Db, notifiers: &Notifiers) {
+    loop {
+        let due = match claim_due_reminders(BATCH_SIZE, db).await {
+            Ok(due) => due,
+            Err(e) => return log::warn!("failed to claim due reminders - {}", e),
+        };
+        let count = due.len() as i64;
+
+        futures::future::join_all(due.iter().map(|reminder| send(reminder, notifiers))).await;
+        if count < BATCH_SIZE {
+            return;
+        }
+    }
+}
+
+async fn send(reminder: &DueReminder, notifiers: &Notifiers) {
+    for notifier in notifiers.iter() {
+        if let Err(e) = notifier.notify(reminder).await {
+            log::warn!(
+                "failed to send reminder of note {} through {} - {}",
+                reminder.note_id,
+                notifier.name(),
+                e
+            );
+        }
+    }
+}
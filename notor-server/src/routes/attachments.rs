@@ -0,0 +1,54 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_auth_header_or_cookie, with_blobs, with_db};
+use crate::blobs::Blobs;
+use crate::db::Db;
+use crate::handlers::attachments::*;
+use notor_core::models::UserRole;
+
+/// Largest accepted upload in bytes, all files of a single request together
+const MAX_UPLOAD_SIZE: u64 = 25 * 1024 * 1024;
+
+pub(crate) fn ro_post_attachments(
+    db: Db,
+    blobs: Blobs,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "attachments")
+        .and(warp::post())
+        .and(warp::multipart::form().max_length(MAX_UPLOAD_SIZE))
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_blobs(blobs))
+        .and_then(post_attachments)
+}
+pub(crate) fn ro_get_note_attachments(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "attachments")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_note_attachments)
+}
+pub(crate) fn ro_get_attachment(
+    db: Db,
+    blobs: Blobs,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("attachments" / i32)
+        .and(warp::get())
+        .and(with_auth_header_or_cookie(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_blobs(blobs))
+        .and_then(get_attachment)
+}
+pub(crate) fn ro_delete_attachment(
+    db: Db,
+    blobs: Blobs,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("attachments" / i32)
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_blobs(blobs))
+        .and_then(delete_attachment)
+}
@@ -0,0 +1,44 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::handlers::calendar::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_calendar_feed(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("calendar")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_calendar_feed)
+}
+pub(crate) fn ro_put_calendar_feed(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("calendar")
+        .and(warp::put())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(put_calendar_feed)
+}
+pub(crate) fn ro_delete_calendar_feed(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("calendar")
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(delete_calendar_feed)
+}
+
+// calendar apps subscribe without logging in, the token in the path is the credential
+pub(crate) fn ro_get_calendar(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("calendar" / String)
+        .and(warp::get())
+        .and(with_db(db))
+        .and_then(get_calendar)
+}
@@ -0,0 +1,20 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header_or_cookie, with_db, with_events};
+use crate::db::Db;
+use crate::events::Events;
+use crate::handlers::events::*;
+use notor_core::models::UserRole;
+
+// browsers can't set headers of `EventSource` requests so the cookie is accepted as well
+pub(crate) fn ro_get_events(
+    db: Db,
+    events: Events,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("events")
+        .and(warp::get())
+        .and(with_auth_header_or_cookie(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_events(events))
+        .and_then(get_events)
+}
@@ -0,0 +1,16 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::filters::GraphQuery;
+use crate::handlers::graph::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_graph(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("graph")
+        .and(warp::get())
+        .and(warp::filters::query::query::<GraphQuery>())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_graph)
+}
@@ -0,0 +1,20 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header_or_cookie, with_db, with_sessions};
+use crate::db::Db;
+use crate::handlers::live::*;
+use crate::live::Sessions;
+use notor_core::models::UserRole;
+
+// browsers can't set headers of websocket requests so the cookie is accepted as well
+pub(crate) fn ro_note_live(
+    db: Db,
+    sessions: Sessions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "live")
+        .and(warp::ws())
+        .and(with_auth_header_or_cookie(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_sessions(sessions))
+        .and_then(note_live)
+}
@@ -1,30 +1,83 @@
+mod attachments;
 mod auth;
+mod calendar;
+mod events;
+mod graph;
+mod live;
+mod notebooks;
 mod notes;
+mod public_links;
+mod reminders;
+mod saved_searches;
+mod settings;
+mod shares;
 mod tags;
+mod tasks;
+mod webhooks;
 
+use serde::de::DeserializeOwned;
 use std::convert::Infallible;
 use warp::{
+    body,
     filters::cookie::cookie,
     filters::header::headers_cloned,
     http::header::{HeaderMap, HeaderValue},
-    Filter, Rejection, Reply,
+    hyper::body::Bytes,
+    reject, Filter, Rejection, Reply,
 };
 
 use crate::auth::BEARER_COOKIE;
+use crate::blobs::Blobs;
 use crate::db::Db;
+use crate::events::Events;
 use crate::handlers::auth::{authorize_headers, authorize_token};
+use crate::live::Sessions;
 use crate::rejections::handle_rejection;
+use crate::webhooks::Webhooks;
+use crate::Error;
 use notor_core::models::UserRole;
 
+use attachments::*;
 use auth::*;
+use calendar::*;
+use events::*;
+use graph::*;
+use live::*;
+use notebooks::*;
 use notes::*;
+use public_links::*;
+use reminders::*;
+use saved_searches::*;
+use settings::*;
+use shares::*;
 use tags::*;
+use tasks::*;
+use webhooks::*;
 
 fn with_db(db: Db) -> impl Filter<Extract = (Db,), Error = Infallible> + Clone {
     warp::any().map(move || db.clone())
 }
 
-#[allow(dead_code)]
+fn with_blobs(blobs: Blobs) -> impl Filter<Extract = (Blobs,), Error = Infallible> + Clone {
+    warp::any().map(move || blobs.clone())
+}
+
+fn with_sessions(
+    sessions: Sessions,
+) -> impl Filter<Extract = (Sessions,), Error = Infallible> + Clone {
+    warp::any().map(move || sessions.clone())
+}
+
+fn with_events(events: Events) -> impl Filter<Extract = (Events,), Error = Infallible> + Clone {
+    warp::any().map(move || events.clone())
+}
+
+fn with_webhooks(
+    webhooks: Webhooks,
+) -> impl Filter<Extract = (Webhooks,), Error = Infallible> + Clone {
+    warp::any().map(move || webhooks.clone())
+}
+
 fn with_auth_cookie(
     role: UserRole,
     db: Db,
@@ -34,6 +87,16 @@ fn with_auth_cookie(
         .and_then(authorize_token)
 }
 
+/// Deserializes a JSON body regardless of its content type so that patches can be sent
+/// as `application/merge-patch+json`.
+fn merge_patch_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    body::bytes().and_then(|body: Bytes| async move {
+        serde_json::from_slice(&body)
+            .map_err(|e| reject::custom(Error::InvalidPatch(e.to_string())))
+    })
+}
+
 pub fn with_auth_header(
     role: UserRole,
     db: Db,
@@ -43,25 +106,139 @@ pub fn with_auth_header(
         .and_then(authorize_headers)
 }
 
-pub fn routes(db: Db) -> impl Filter<Extract = impl Reply, Error = Infallible> + Clone {
+/// Takes the token from the authentication header or, when it's missing, from the cookie
+/// so that browsers can load resources like images embedded in pages.
+fn with_auth_header_or_cookie(
+    role: UserRole,
+    db: Db,
+) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    with_auth_header(role.clone(), db.clone())
+        .or(with_auth_cookie(role, db))
+        .unify()
+}
+
+/// Builds all routes of the API. Background work like listening for events is started by
+/// the caller so that building the routes has no side effects, see [`Events::listen`],
+/// [`Webhooks::start`] and [`Sessions::start`].
+pub fn routes(
+    db: Db,
+    blobs: Blobs,
+    events: Events,
+    webhooks: Webhooks,
+    sessions: Sessions,
+) -> impl Filter<Extract = impl Reply, Error = Infallible> + Clone {
+    // groups are boxed, a single chain of all routes overflows the stack of debug builds
     let notes_routes = ro_get_notes(db.clone())
         .or(ro_get_note(db.clone()))
         .or(ro_put_note(db.clone()))
-        .or(ro_delete_note(db.clone()))
-        .or(ro_update_note(db.clone()))
+        .or(ro_delete_note(db.clone(), blobs.clone()))
+        .or(ro_update_note(db.clone(), sessions.clone()))
+        .or(ro_patch_note(db.clone(), sessions.clone()))
         .or(ro_tag_note(db.clone()))
         .or(ro_untag_note(db.clone()))
-        .or(ro_get_note_tags(db.clone()));
+        .or(ro_move_note(db.clone()))
+        .or(ro_unfile_note(db.clone()))
+        .or(ro_pin_note(db.clone()))
+        .or(ro_unpin_note(db.clone()))
+        .or(ro_favorite_note(db.clone()))
+        .or(ro_unfavorite_note(db.clone()))
+        .or(ro_archive_note(db.clone()))
+        .or(ro_unarchive_note(db.clone()))
+        .or(ro_snooze_note_reminder(db.clone()))
+        .or(ro_get_note_backlinks(db.clone()))
+        .or(ro_get_note_links(db.clone()))
+        .or(ro_get_note_tags(db.clone()))
+        .or(ro_get_note_revisions(db.clone()))
+        .or(ro_get_note_tasks(db.clone()))
+        .or(ro_toggle_note_task(db.clone(), sessions.clone()))
+        .boxed();
 
     let tags_routes = ro_get_tags(db.clone())
         .or(ro_get_tag(db.clone()))
         .or(ro_put_tag(db.clone()))
-        .or(ro_delete_tag(db.clone()));
+        .or(ro_update_tag(db.clone()))
+        .or(ro_patch_tag(db.clone()))
+        .or(ro_merge_tag(db.clone()))
+        .or(ro_delete_tag(db.clone()))
+        .boxed();
+
+    let notebooks_routes = ro_get_notebooks(db.clone())
+        .or(ro_get_notebook(db.clone()))
+        .or(ro_put_notebook(db.clone()))
+        .or(ro_update_notebook(db.clone()))
+        .or(ro_delete_notebook(db.clone()))
+        .boxed();
+
+    let saved_searches_routes = ro_get_saved_searches(db.clone())
+        .or(ro_get_saved_search(db.clone()))
+        .or(ro_get_saved_search_notes(db.clone()))
+        .or(ro_put_saved_search(db.clone()))
+        .or(ro_update_saved_search(db.clone()))
+        .or(ro_delete_saved_search(db.clone()))
+        .boxed();
+
+    let attachments_routes = ro_post_attachments(db.clone(), blobs.clone())
+        .or(ro_get_note_attachments(db.clone()))
+        .or(ro_get_attachment(db.clone(), blobs.clone()))
+        .or(ro_delete_attachment(db.clone(), blobs))
+        .boxed();
+
+    let shares_routes = ro_get_shared_notes(db.clone())
+        .or(ro_get_note_shares(db.clone()))
+        .or(ro_put_note_share(db.clone()))
+        .or(ro_delete_note_share(db.clone()))
+        .boxed();
+
+    let public_links_routes = ro_get_public_links(db.clone())
+        .or(ro_put_public_link(db.clone()))
+        .or(ro_delete_public_link(db.clone()))
+        .or(ro_get_public_note(db.clone()))
+        .or(ro_post_public_note(db.clone()))
+        .boxed();
+
+    let live_routes = ro_note_live(db.clone(), sessions);
+
+    let webhooks_routes = ro_get_webhooks(db.clone())
+        .or(ro_put_webhook(db.clone()))
+        .or(ro_update_webhook(db.clone()))
+        .or(ro_delete_webhook(db.clone()))
+        .or(ro_get_webhook_deliveries(db.clone()))
+        .or(ro_test_webhook(db.clone(), webhooks))
+        .boxed();
+
+    let events_routes = ro_get_events(db.clone(), events);
+
+    let tasks_routes = ro_get_tasks(db.clone());
+
+    let reminders_routes = ro_get_upcoming_reminders(db.clone());
+
+    let calendar_routes = ro_get_calendar_feed(db.clone())
+        .or(ro_put_calendar_feed(db.clone()))
+        .or(ro_delete_calendar_feed(db.clone()))
+        .or(ro_get_calendar(db.clone()))
+        .boxed();
+
+    let graph_routes = ro_get_graph(db.clone());
+
+    let settings_routes = ro_get_settings(db.clone()).or(ro_update_settings(db.clone()));
 
     let auth_routes = ro_auth(db.clone());
 
     notes_routes
         .or(tags_routes)
+        .or(notebooks_routes)
+        .or(saved_searches_routes)
+        .or(attachments_routes)
+        .or(shares_routes)
+        .or(public_links_routes)
+        .or(live_routes)
+        .or(events_routes)
+        .or(webhooks_routes)
+        .or(tasks_routes)
+        .or(reminders_routes)
+        .or(calendar_routes)
+        .or(graph_routes)
+        .or(settings_routes)
         .or(auth_routes)
         .recover(handle_rejection)
         .with(warp::log("notor::routes"))
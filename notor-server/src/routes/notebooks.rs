@@ -0,0 +1,55 @@
+use warp::body;
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::handlers::notebooks::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_notebooks(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notebooks")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_notebooks)
+}
+pub(crate) fn ro_get_notebook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notebooks" / i32)
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_notebook)
+}
+pub(crate) fn ro_put_notebook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notebooks")
+        .and(warp::put())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(put_notebook)
+}
+pub(crate) fn ro_update_notebook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notebooks" / i32)
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(update_notebook)
+}
+pub(crate) fn ro_delete_notebook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notebooks" / i32)
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(delete_notebook)
+}
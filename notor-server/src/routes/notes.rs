@@ -1,16 +1,19 @@
 use warp::body;
 use warp::{Filter, Rejection, Reply};
 
-use super::{with_auth_header, with_db};
+use super::{merge_patch_body, with_auth_header, with_blobs, with_db, with_sessions};
+use crate::blobs::Blobs;
 use crate::db::Db;
-use crate::filters::QueryFilter;
+use crate::filters::{LinkQuery, QueryFilter, TimeQuery};
 use crate::handlers::notes::*;
+use crate::live::Sessions;
 use notor_core::models::UserRole;
 
 pub(crate) fn ro_get_notes(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("notes")
         .and(warp::get())
         .and(warp::filters::query::query::<QueryFilter>())
+        .and(warp::filters::query::query::<TimeQuery>())
         .and(with_auth_header(UserRole::User, db.clone()))
         .and(with_db(db))
         .and_then(get_notes)
@@ -18,6 +21,7 @@ pub(crate) fn ro_get_notes(db: Db) -> impl Filter<Extract = impl Reply, Error =
 pub(crate) fn ro_get_note(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("notes" / i32)
         .and(warp::get())
+        .and(warp::filters::query::query::<TimeQuery>())
         .and(with_auth_header(UserRole::User, db.clone()))
         .and(with_db(db))
         .and_then(get_note)
@@ -32,23 +36,41 @@ pub(crate) fn ro_put_note(db: Db) -> impl Filter<Extract = impl Reply, Error = R
 }
 pub(crate) fn ro_delete_note(
     db: Db,
+    blobs: Blobs,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("notes" / i32)
         .and(warp::delete())
         .and(with_auth_header(UserRole::User, db.clone()))
         .and(with_db(db))
+        .and(with_blobs(blobs))
         .and_then(delete_note)
 }
 pub(crate) fn ro_update_note(
     db: Db,
+    sessions: Sessions,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("notes" / i32)
         .and(warp::post())
         .and(body::json())
+        .and(warp::filters::query::query::<LinkQuery>())
         .and(with_auth_header(UserRole::User, db.clone()))
         .and(with_db(db))
+        .and(with_sessions(sessions))
         .and_then(update_note)
 }
+pub(crate) fn ro_patch_note(
+    db: Db,
+    sessions: Sessions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32)
+        .and(warp::patch())
+        .and(merge_patch_body())
+        .and(warp::filters::query::query::<LinkQuery>())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_sessions(sessions))
+        .and_then(patch_note)
+}
 
 pub(crate) fn ro_tag_note(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("notes" / i32 / "tags" / String)
@@ -67,6 +89,136 @@ pub(crate) fn ro_untag_note(
         .and_then(untag_note)
 }
 
+pub(crate) fn ro_move_note(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "notebook" / i32)
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(move_note)
+}
+pub(crate) fn ro_unfile_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "notebook")
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(unfile_note)
+}
+
+pub(crate) fn ro_pin_note(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "pin")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(pin_note)
+}
+pub(crate) fn ro_unpin_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "pin")
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(unpin_note)
+}
+pub(crate) fn ro_favorite_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "favorite")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(favorite_note)
+}
+pub(crate) fn ro_unfavorite_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "favorite")
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(unfavorite_note)
+}
+pub(crate) fn ro_archive_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "archive")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(archive_note)
+}
+pub(crate) fn ro_snooze_note_reminder(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "reminder" / "snooze")
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(snooze_note_reminder)
+}
+pub(crate) fn ro_unarchive_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "unarchive")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(unarchive_note)
+}
+pub(crate) fn ro_get_note_backlinks(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "backlinks")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_note_backlinks)
+}
+pub(crate) fn ro_get_note_links(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "links")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_note_links)
+}
+
+pub(crate) fn ro_get_note_tasks(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "tasks")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_note_tasks)
+}
+
+pub(crate) fn ro_toggle_note_task(
+    db: Db,
+    sessions: Sessions,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "tasks" / usize / "toggle")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_sessions(sessions))
+        .and_then(toggle_note_task)
+}
+
+pub(crate) fn ro_get_note_revisions(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "revisions")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_note_revisions)
+}
+
 pub(crate) fn ro_get_note_tags(
     db: Db,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
@@ -0,0 +1,55 @@
+use warp::body;
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::handlers::public_links::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_public_links(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "public-links")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_public_links)
+}
+pub(crate) fn ro_put_public_link(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "public-links")
+        .and(warp::put())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(put_public_link)
+}
+pub(crate) fn ro_delete_public_link(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("public-links" / i32)
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(delete_public_link)
+}
+
+// links are opened by people without an account, these routes don't authenticate
+pub(crate) fn ro_get_public_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("public" / String)
+        .and(warp::get())
+        .and(with_db(db))
+        .and_then(get_public_note)
+}
+pub(crate) fn ro_post_public_note(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("public" / String)
+        .and(warp::post())
+        .and(body::json())
+        .and(with_db(db))
+        .and_then(post_public_note)
+}
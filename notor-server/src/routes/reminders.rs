@@ -0,0 +1,18 @@
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::filters::ReminderQuery;
+use crate::handlers::reminders::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_upcoming_reminders(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reminders" / "upcoming")
+        .and(warp::get())
+        .and(warp::filters::query::query::<ReminderQuery>())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_upcoming_reminders)
+}
@@ -0,0 +1,66 @@
+use warp::body;
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::filters::TimeQuery;
+use crate::handlers::saved_searches::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_saved_searches(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_saved_searches)
+}
+pub(crate) fn ro_get_saved_search(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches" / i32)
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_saved_search)
+}
+pub(crate) fn ro_get_saved_search_notes(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches" / i32 / "notes")
+        .and(warp::get())
+        .and(warp::filters::query::query::<TimeQuery>())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_saved_search_notes)
+}
+pub(crate) fn ro_put_saved_search(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches")
+        .and(warp::put())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(put_saved_search)
+}
+pub(crate) fn ro_update_saved_search(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches" / i32)
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(update_saved_search)
+}
+pub(crate) fn ro_delete_saved_search(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("saved-searches" / i32)
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(delete_saved_search)
+}
@@ -0,0 +1,27 @@
+use warp::body;
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db};
+use crate::db::Db;
+use crate::handlers::settings::*;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_settings(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("settings")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_settings)
+}
+pub(crate) fn ro_update_settings(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("settings")
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(update_settings)
+}
@@ -1,16 +1,16 @@
 use warp::body;
 use warp::{Filter, Rejection, Reply};
 
-use super::{with_auth_header, with_db};
+use super::{merge_patch_body, with_auth_header, with_db};
 use crate::db::Db;
-use crate::filters::QueryFilter;
+use crate::filters::TagQuery;
 use crate::handlers::tags::*;
 use notor_core::models::UserRole;
 
 pub(crate) fn ro_get_tags(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     warp::path!("tags")
         .and(warp::get())
-        .and(warp::filters::query::query::<QueryFilter>())
+        .and(warp::filters::query::query::<TagQuery>())
         .and(with_auth_header(UserRole::User, db.clone()))
         .and(with_db(db))
         .and_then(get_tags)
@@ -30,6 +30,32 @@ pub(crate) fn ro_put_tag(db: Db) -> impl Filter<Extract = impl Reply, Error = Re
         .and(with_db(db))
         .and_then(put_tag)
 }
+pub(crate) fn ro_update_tag(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tags" / i32)
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(update_tag)
+}
+pub(crate) fn ro_patch_tag(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tags" / i32)
+        .and(warp::patch())
+        .and(merge_patch_body())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(patch_tag)
+}
+pub(crate) fn ro_merge_tag(db: Db) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tags" / i32 / "merge")
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(merge_tag)
+}
 pub(crate) fn ro_delete_tag(
     db: Db,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
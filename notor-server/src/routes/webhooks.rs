@@ -0,0 +1,67 @@
+use warp::body;
+use warp::{Filter, Rejection, Reply};
+
+use super::{with_auth_header, with_db, with_webhooks};
+use crate::db::Db;
+use crate::handlers::webhooks::*;
+use crate::webhooks::Webhooks;
+use notor_core::models::UserRole;
+
+pub(crate) fn ro_get_webhooks(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_webhooks)
+}
+pub(crate) fn ro_put_webhook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks")
+        .and(warp::put())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(put_webhook)
+}
+pub(crate) fn ro_update_webhook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks" / i32)
+        .and(warp::post())
+        .and(body::json())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(update_webhook)
+}
+pub(crate) fn ro_delete_webhook(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks" / i32)
+        .and(warp::delete())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(delete_webhook)
+}
+pub(crate) fn ro_get_webhook_deliveries(
+    db: Db,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks" / i32 / "deliveries")
+        .and(warp::get())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and_then(get_webhook_deliveries)
+}
+pub(crate) fn ro_test_webhook(
+    db: Db,
+    webhooks: Webhooks,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("webhooks" / i32 / "test")
+        .and(warp::post())
+        .and(with_auth_header(UserRole::User, db.clone()))
+        .and(with_db(db))
+        .and(with_webhooks(webhooks))
+        .and_then(test_webhook)
+}
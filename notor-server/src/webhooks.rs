@@ -0,0 +1,370 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{redirect, Url};
+use sha2::Sha256;
+use std::env;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::lookup_host;
+use tokio::sync::broadcast::Receiver;
+
+use crate::db::Db;
+use crate::events::Events;
+use crate::models::{
+    claim_webhook_deliveries, finish_webhook_delivery, queue_webhook_delivery, ClaimedDelivery,
+};
+use crate::Error;
+use notor_core::models::{DeliveryStatus, Event, Webhook, WebhookDelivery, WEBHOOK_TEST_EVENT};
+
+pub const SIGNATURE_HEADER: &str = "X-Notor-Signature";
+pub const EVENT_HEADER: &str = "X-Notor-Event";
+pub const DELIVERY_HEADER: &str = "X-Notor-Delivery";
+/// Comma separated hosts of webhook urls that may resolve to loopback or private
+/// addresses, like `localhost` for a receiver running next to the server
+pub const ALLOWED_HOSTS_ENV: &str = "NOTOR_WEBHOOK_ALLOWED_HOSTS";
+
+/// Deliveries attempted at once by a server instance
+const BATCH_SIZE: i64 = 10;
+/// Time between looking for due retries when no change wakes up the delivery earlier
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Claimed deliveries that weren't finished by then, like when the server instance
+/// stopped, are attempted again. Has to be longer than `TIMEOUT`.
+const CLAIM_SECS: i64 = 60;
+/// Attempts made before giving up, retries wait 10 s, 20 s, 40 s, 80 s and 160 s
+const MAX_ATTEMPTS: i32 = 6;
+const RETRY_BASE_SECS: i64 = 10;
+
+/// Signs the body with HMAC-SHA256, the result is sent in the `X-Notor-Signature` header.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn claimed_until() -> chrono::DateTime<Utc> {
+    Utc::now() + chrono::Duration::seconds(CLAIM_SECS)
+}
+
+/// Redirects aren't followed, they could lead to addresses [`check_target`] rejects.
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().redirect(redirect::Policy::none())
+}
+
+/// Whether the address is reachable on the public internet, std's `is_global` isn't stable.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            // IPv4 mapped and NAT64 addresses reach the embedded IPv4 address
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_v4(ip);
+            }
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_v4(Ipv4Addr::new(a, b, c, d));
+            }
+
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // unique local fc00::/7
+                || segments[0] & 0xfe00 == 0xfc00
+                // link local fe80::/10
+                || segments[0] & 0xffc0 == 0xfe80
+                // documentation 2001:db8::/32
+                || segments[..2] == [0x2001, 0xdb8]
+                // IPv4 compatible ::/96
+                || segments[..6] == [0; 6])
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // this network 0.0.0.0/8 and reserved 240.0.0.0/4
+        || a == 0
+        || a >= 240
+        // shared address space 100.64.0.0/10
+        || (a == 100 && b & 0xc0 == 64)
+        // protocol assignments 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // benchmarking 198.18.0.0/15
+        || (a == 198 && b & 0xfe == 18))
+}
+
+fn allowed_hosts() -> Vec<String> {
+    env::var(ALLOWED_HOSTS_ENV)
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// Makes sure that the webhook url points to a public address so that webhooks can't
+/// reach the internal network of the server, hosts allowed by
+/// `NOTOR_WEBHOOK_ALLOWED_HOSTS` skip the check. Returns the domain of the url with the
+/// address it resolved to, requests have to connect there as the domain could resolve
+/// differently next time.
+pub async fn check_target(url: &str) -> Result<Option<(String, SocketAddr)>, Error> {
+    resolve_target(url, &allowed_hosts()).await
+}
+
+async fn resolve_target(
+    url: &str,
+    allowed_hosts: &[String],
+) -> Result<Option<(String, SocketAddr)>, Error> {
+    let invalid = |reason: String| Error::InvalidWebhook(format!("`{}` {}", url, reason));
+    let parsed = Url::parse(url).map_err(|e| invalid(e.to_string()))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| invalid("has no host".into()))?;
+    if allowed_hosts.iter().any(|allowed| allowed == host) {
+        return Ok(None);
+    }
+
+    let not_public = |ip: IpAddr| invalid(format!("points to the non-public address {}", ip));
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse() {
+        return if is_public(ip) {
+            Ok(None)
+        } else {
+            Err(not_public(ip))
+        };
+    }
+
+    let port = parsed.port_or_known_default().unwrap_or_default();
+    let addrs: Vec<_> = lookup_host((host, port))
+        .await
+        .map_err(|e| invalid(format!("can't be resolved - {}", e)))?
+        .collect();
+    // any of them could be used to connect
+    if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        return Err(not_public(addr.ip()));
+    }
+
+    match addrs.first() {
+        Some(addr) => Ok(Some((host.to_string(), *addr))),
+        None => Err(invalid("can't be resolved".into())),
+    }
+}
+
+/// Sends deliveries queued by the database for webhooks subscribed to events.
+#[derive(Clone)]
+pub struct Webhooks {
+    client: reqwest::Client,
+    db: Db,
+}
+
+impl Webhooks {
+    /// Starts delivering in the background, changes announced by `events` are delivered
+    /// right away.
+    pub fn start(db: Db, events: &Events) -> Self {
+        let webhooks = Webhooks {
+            client: client_builder()
+                .build()
+                .expect("default TLS backend is available"),
+            db,
+        };
+        tokio::spawn(webhooks.clone().run(events.subscribe_all()));
+        webhooks
+    }
+
+    async fn run(self, mut changes: Receiver<Event>) {
+        loop {
+            self.deliver_due().await;
+            tokio::select! {
+                _ = changes.recv() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn deliver_due(&self) {
+        loop {
+            let due = match claim_webhook_deliveries(BATCH_SIZE, claimed_until(), &self.db).await {
+                Ok(due) => due,
+                Err(e) => return log::warn!("failed to claim webhook deliveries - {}", e),
+            };
+            let count = due.len() as i64;
+
+            for result in futures::future::join_all(due.into_iter().map(|d| self.attempt(d))).await
+            {
+                if let Err(e) = result {
+                    log::warn!("failed to record webhook delivery - {}", e);
+                }
+            }
+            if count < BATCH_SIZE {
+                return;
+            }
+        }
+    }
+
+    /// Sends a test event to the webhook right away.
+    pub async fn test(&self, webhook: &Webhook) -> Result<WebhookDelivery, Error> {
+        let payload = serde_json::json!({
+            "event": WEBHOOK_TEST_EVENT,
+            "webhook_id": webhook.id,
+            "user_id": webhook.user_id,
+            "created": Utc::now(),
+        });
+        let delivery = queue_webhook_delivery(
+            webhook,
+            WEBHOOK_TEST_EVENT,
+            &payload,
+            claimed_until(),
+            &self.db,
+        )
+        .await?;
+
+        self.attempt(delivery).await
+    }
+
+    /// Client for requests to `url` connecting to the address checked by [`check_target`].
+    async fn client_for(&self, url: &str) -> Result<reqwest::Client, Error> {
+        match check_target(url).await? {
+            Some((domain, addr)) => client_builder()
+                .resolve(&domain, addr)
+                .build()
+                .map_err(|e| Error::InvalidWebhook(e.to_string())),
+            None => Ok(self.client.clone()),
+        }
+    }
+
+    /// Sends the delivery once, a failed attempt is retried later with exponential
+    /// backoff until it runs out of attempts.
+    async fn attempt(&self, delivery: ClaimedDelivery) -> Result<WebhookDelivery, Error> {
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let response = match self.client_for(&delivery.url).await {
+            Ok(client) => client
+                .post(&delivery.url)
+                .timeout(TIMEOUT)
+                .header(CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, &delivery.event)
+                .header(DELIVERY_HEADER, delivery.id)
+                .header(SIGNATURE_HEADER, sign(&delivery.secret, &body))
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let (response_status, error) = match response {
+            Ok(response) if response.status().is_success() => (Some(response.status()), None),
+            Ok(response) => (
+                Some(response.status()),
+                Some(format!("receiver responded with `{}`", response.status())),
+            ),
+            Err(e) => (None, Some(e)),
+        };
+
+        let attempts = delivery.attempts + 1;
+        let (status, next_attempt) = match error {
+            None => (DeliveryStatus::Succeeded, None),
+            Some(_) if attempts >= MAX_ATTEMPTS => (DeliveryStatus::Failed, None),
+            Some(_) => {
+                let delay = RETRY_BASE_SECS << (attempts - 1);
+                (
+                    DeliveryStatus::Pending,
+                    Some(Utc::now() + chrono::Duration::seconds(delay)),
+                )
+            }
+        };
+
+        finish_webhook_delivery(
+            delivery.id,
+            status,
+            response_status.map(|status| i32::from(status.as_u16())),
+            error.as_deref(),
+            next_attempt,
+            &self.db,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_addresses() {
+        for ip in &["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in &[
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+            "64:ff9b::a00:1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_non_public_targets() {
+        for url in &[
+            "http://127.0.0.1:3693/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://localhost:9000/",
+            "http://0x7f000001/",
+        ] {
+            assert!(
+                matches!(
+                    resolve_target(url, &[]).await,
+                    Err(Error::InvalidWebhook(_))
+                ),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn allows_public_and_allowed_targets() {
+        assert!(resolve_target("https://93.184.216.34/hook", &[])
+            .await
+            .unwrap()
+            .is_none());
+
+        let allowed = ["localhost".to_string(), "[::1]".to_string()];
+        assert!(resolve_target("http://localhost:9000/", &allowed)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(resolve_target("http://[::1]:9000/", &allowed)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(resolve_target("http://127.0.0.1:9000/", &allowed)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,172 @@
+language-name = English
+
+# dates are formatted with strftime patterns
+datetime-format = %b %-d, %Y %-I:%M %p
+
+page-title = notor
+page-title-login = notor - login
+page-title-tag = notor - { $tag }
+page-title-archive = notor - archive
+page-title-graph = notor - graph
+page-title-shared = notor - shared with me
+page-title-webhooks = notor - webhooks
+page-title-tasks = notor - tasks
+page-title-reminders = notor - reminders
+page-title-link-password = notor - protected note
+
+theme-label = Theme:
+theme-auto = System default
+theme-light = Light
+theme-dark = Dark
+theme-high-contrast = High contrast
+
+language-label = Language:
+language-auto = Browser default
+
+timezone-label = Time zone:
+
+notebooks-header = Notebooks
+all-notes = All notes
+new-notebook-placeholder = New notebook...
+
+saved-searches-header = Saved searches
+save-search-placeholder = Save current search as...
+search-placeholder = Search notes...
+tags-filter-placeholder = Tags, e.g. work, -archive
+
+pinned-header = Pinned
+pin-label = Pin
+favorite-label = Favorite
+archive-label = Archive
+unarchive-label = Restore
+
+notes-header = Notes:
+column-created = Created
+column-title = Title
+column-tags = Tags
+no-notes = No notes available... Add a note below.
+
+add-note-header = Add a note
+title-label = Title:
+content-label = Content:
+content-placeholder = Enter your note here...
+
+created-at = Created at { $date }
+backlinks-header = Linked from
+attachments-header = Attachments
+live-header = Live editing
+live-present = Open by:
+attachment-upload = Upload
+attachment-size = { $size } B
+shares-header = Shared with
+share-label = Share
+share-username-placeholder = Username...
+share-viewer = Viewer
+share-editor = Editor
+shared-as-viewer = Shared with you, you can view it
+shared-as-editor = Shared with you, you can edit it
+public-links-header = Public links
+public-link-create = Create link
+public-link-revoke = Revoke
+public-link-protected = Protected by a password
+public-link-expires-label = Expires:
+public-link-password-placeholder = Password (optional)
+public-link-expires = expires { $date }
+public-link-views = { $count ->
+    [one] 1 view
+   *[other] { $count } views
+}
+column-edited = Edited
+edited-just-now = edited just now
+edited-minutes-ago = edited { $count ->
+    [one] a minute ago
+   *[other] { $count } minutes ago
+}
+edited-hours-ago = edited { $count ->
+    [one] an hour ago
+   *[other] { $count } hours ago
+}
+edited-days-ago = edited { $count ->
+    [one] yesterday
+   *[other] { $count } days ago
+}
+edited-on = edited on { $date }
+tag-label = Tag:
+
+notes-with-tag = Notes with tag
+no-tagged-notes = No notes with selected tag available...
+
+archive-header = Archive
+archive-link = Archive
+column-archived = Archived
+no-archived-notes = No archived notes...
+
+shared-header = Shared with me
+shared-link = Shared with me
+column-owner = Owner
+column-permission = Permission
+share-leave-label = Remove from shared
+no-shared-notes = No notes are shared with you...
+
+tasks-header = Tasks
+tasks-link = Tasks
+column-task = Task
+column-note = Note
+no-open-tasks = Nothing left to do...
+
+reminders-header = Upcoming reminders
+reminders-link = Reminders
+column-remind-at = Reminder
+column-due = Due
+no-reminders = No upcoming reminders...
+reminder-header = Reminder
+reminder-at = Reminder at { $date }
+reminder-sent = Reminded at { $date }
+due-at = Due { $date }
+due-label = Due:
+remind-label = Remind me:
+reminder-save = Save reminder
+reminder-clear = Remove reminder
+due-clear = Remove due date
+snooze-label = Snooze for
+snooze-10m = 10 minutes
+snooze-1h = 1 hour
+snooze-1d = 1 day
+calendar-feed-header = Calendar feed
+calendar-feed-info = Subscribe to this address in a calendar app to see notes with due dates. Anyone with the address can read it.
+calendar-feed-create = Create calendar feed
+calendar-feed-regenerate = New address
+calendar-feed-revoke = Revoke
+calendar-feed-created = created { $date }
+
+webhooks-header = Webhooks
+webhooks-link = Webhooks
+no-webhooks = No webhooks yet...
+webhook-test = Send a test event
+webhook-delete = Delete webhook
+webhook-only-tag = only with tag { $tag }
+webhook-secret = Signing secret:
+no-webhook-deliveries = Nothing was delivered yet...
+column-event = Event
+column-status = Status
+column-attempts = Attempts
+column-response = Response
+delivery-pending = Pending
+delivery-succeeded = Delivered
+delivery-failed = Failed
+delivery-retry = retrying { $date }
+new-webhook-header = New webhook
+webhook-url-placeholder = https://...
+webhook-tag-placeholder = Only with tag (optional)
+webhook-add = Add webhook
+
+graph-header = Graph
+graph-link = Graph
+graph-depth-label = Depth:
+graph-show = Show
+graph-whole = Whole graph
+
+username-label = Username:
+password-label = Password:
+link-password-label = This note is protected, enter the password:
+link-password-invalid = Invalid password
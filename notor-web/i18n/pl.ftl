@@ -0,0 +1,178 @@
+language-name = Polski
+
+# dates are formatted with strftime patterns
+datetime-format = %d.%m.%Y %H:%M
+
+page-title = notor
+page-title-login = notor - logowanie
+page-title-tag = notor - { $tag }
+page-title-archive = notor - archiwum
+page-title-graph = notor - graf
+page-title-shared = notor - udostępnione dla mnie
+page-title-webhooks = notor - webhooki
+page-title-tasks = notor - zadania
+page-title-reminders = notor - przypomnienia
+page-title-link-password = notor - chroniona notatka
+
+theme-label = Motyw:
+theme-auto = Systemowy
+theme-light = Jasny
+theme-dark = Ciemny
+theme-high-contrast = Wysoki kontrast
+
+language-label = Język:
+language-auto = Domyślny przeglądarki
+
+timezone-label = Strefa czasowa:
+
+notebooks-header = Notatniki
+all-notes = Wszystkie notatki
+new-notebook-placeholder = Nowy notatnik...
+
+saved-searches-header = Zapisane wyszukiwania
+save-search-placeholder = Zapisz wyszukiwanie jako...
+search-placeholder = Szukaj w notatkach...
+tags-filter-placeholder = Tagi, np. praca, -archiwum
+
+pinned-header = Przypięte
+pin-label = Przypnij
+favorite-label = Ulubiona
+archive-label = Archiwizuj
+unarchive-label = Przywróć
+
+notes-header = Notatki:
+column-created = Utworzono
+column-title = Tytuł
+column-tags = Tagi
+no-notes = Brak notatek... Dodaj notatkę poniżej.
+
+add-note-header = Dodaj notatkę
+title-label = Tytuł:
+content-label = Treść:
+content-placeholder = Wpisz tutaj swoją notatkę...
+
+created-at = Utworzono { $date }
+backlinks-header = Odnośniki z
+attachments-header = Załączniki
+live-header = Edycja na żywo
+live-present = Otwarte przez:
+attachment-upload = Wyślij
+attachment-size = { $size } B
+shares-header = Udostępniono dla
+share-label = Udostępnij
+share-username-placeholder = Nazwa użytkownika...
+share-viewer = Przeglądający
+share-editor = Edytujący
+shared-as-viewer = Udostępniona dla ciebie do przeglądania
+shared-as-editor = Udostępniona dla ciebie do edycji
+public-links-header = Linki publiczne
+public-link-create = Utwórz link
+public-link-revoke = Unieważnij
+public-link-protected = Chroniony hasłem
+public-link-expires-label = Wygasa:
+public-link-password-placeholder = Hasło (opcjonalne)
+public-link-expires = wygasa { $date }
+public-link-views = { $count ->
+    [one] 1 wyświetlenie
+    [few] { $count } wyświetlenia
+    [many] { $count } wyświetleń
+   *[other] { $count } wyświetlenia
+}
+column-edited = Edytowano
+edited-just-now = edytowano przed chwilą
+edited-minutes-ago = edytowano { $count ->
+    [one] minutę temu
+    [few] { $count } minuty temu
+    [many] { $count } minut temu
+   *[other] { $count } minuty temu
+}
+edited-hours-ago = edytowano { $count ->
+    [one] godzinę temu
+    [few] { $count } godziny temu
+    [many] { $count } godzin temu
+   *[other] { $count } godziny temu
+}
+edited-days-ago = edytowano { $count ->
+    [one] wczoraj
+   *[other] { $count } dni temu
+}
+edited-on = edytowano { $date }
+tag-label = Tag:
+
+notes-with-tag = Notatki z tagiem
+no-tagged-notes = Brak notatek z wybranym tagiem...
+
+archive-header = Archiwum
+archive-link = Archiwum
+column-archived = Zarchiwizowano
+no-archived-notes = Brak zarchiwizowanych notatek...
+
+shared-header = Udostępnione dla mnie
+shared-link = Udostępnione dla mnie
+column-owner = Właściciel
+column-permission = Uprawnienia
+share-leave-label = Usuń z udostępnionych
+no-shared-notes = Nikt nie udostępnił ci notatek...
+
+tasks-header = Zadania
+tasks-link = Zadania
+column-task = Zadanie
+column-note = Notatka
+no-open-tasks = Nie ma nic do zrobienia...
+
+reminders-header = Nadchodzące przypomnienia
+reminders-link = Przypomnienia
+column-remind-at = Przypomnienie
+column-due = Termin
+no-reminders = Brak nadchodzących przypomnień...
+reminder-header = Przypomnienie
+reminder-at = Przypomnienie { $date }
+reminder-sent = Przypomniano { $date }
+due-at = Termin { $date }
+due-label = Termin:
+remind-label = Przypomnij:
+reminder-save = Zapisz przypomnienie
+reminder-clear = Usuń przypomnienie
+due-clear = Usuń termin
+snooze-label = Odłóż o
+snooze-10m = 10 minut
+snooze-1h = 1 godzinę
+snooze-1d = 1 dzień
+calendar-feed-header = Kalendarz
+calendar-feed-info = Zasubskrybuj ten adres w aplikacji kalendarza, żeby widzieć notatki z terminami. Każdy, kto zna adres, może go odczytać.
+calendar-feed-create = Utwórz kalendarz
+calendar-feed-regenerate = Nowy adres
+calendar-feed-revoke = Unieważnij
+calendar-feed-created = utworzono { $date }
+
+webhooks-header = Webhooki
+webhooks-link = Webhooki
+no-webhooks = Brak webhooków...
+webhook-test = Wyślij testowe zdarzenie
+webhook-delete = Usuń webhook
+webhook-only-tag = tylko z tagiem { $tag }
+webhook-secret = Sekret podpisu:
+no-webhook-deliveries = Nic jeszcze nie wysłano...
+column-event = Zdarzenie
+column-status = Stan
+column-attempts = Próby
+column-response = Odpowiedź
+delivery-pending = Oczekuje
+delivery-succeeded = Dostarczono
+delivery-failed = Nieudane
+delivery-retry = ponowienie { $date }
+new-webhook-header = Nowy webhook
+webhook-url-placeholder = https://...
+webhook-tag-placeholder = Tylko z tagiem (opcjonalnie)
+webhook-add = Dodaj webhook
+
+graph-header = Graf
+graph-link = Graf
+graph-depth-label = Głębokość:
+graph-show = Pokaż
+graph-whole = Cały graf
+
+username-label = Nazwa użytkownika:
+password-label = Hasło:
+link-password-label = Ta notatka jest chroniona, podaj hasło:
+link-password-invalid = Nieprawidłowe hasło
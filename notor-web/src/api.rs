@@ -0,0 +1,282 @@
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::env;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{self, http::header::AUTHORIZATION, http::Request};
+use tokio_tungstenite::{connect_async, WebSocketStream};
+
+use crate::Error;
+use notor_core::graph::Graph;
+use notor_core::models::{
+    Attachment, CalendarFeed, ErrReply, LinkPassword, Note, NoteShare, NoteTask, NoteWithTags,
+    Notebook, PublicLink, PublicNote, Reminder, SavedSearch, SharedNote, Tag, UserSettings,
+    Webhook, WebhookDelivery,
+};
+
+pub type LiveSocket = WebSocketStream<TcpStream>;
+
+pub const API_URL_ENV: &str = "NOTOR_API_URL";
+const DEFAULT_API_URL: &str = "http://127.0.0.1:3693";
+
+/// Listing options passed through from the page url to the api.
+#[derive(Default, Deserialize, Serialize)]
+pub struct NotesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notebook_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<String>,
+    /// Lists the notes found by this saved search instead, not passed to the api.
+    #[serde(skip_serializing)]
+    pub saved_search: Option<i32>,
+}
+
+/// Neighbourhood of a note shown in the graph view, passed through to the api.
+#[derive(Default, Deserialize, Serialize)]
+pub struct GraphQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+}
+
+/// Thin client of the notor-server REST api. Every call is made on behalf of
+/// the user whose token is passed in.
+#[derive(Clone)]
+pub struct ApiClient {
+    client: Client,
+    url: Arc<String>,
+}
+
+impl ApiClient {
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        ApiClient {
+            client: Client::new(),
+            url: Arc::new(url.into()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(env::var(API_URL_ENV).unwrap_or_else(|_| DEFAULT_API_URL.to_string()))
+    }
+
+    pub fn endpoint<S: AsRef<str>>(&self, path: S) -> String {
+        format!("{}{}", self.url, path.as_ref())
+    }
+
+    pub fn request<S: AsRef<str>>(&self, method: Method, path: S) -> RequestBuilder {
+        self.client.request(method, self.endpoint(path))
+    }
+
+    async fn get<T: DeserializeOwned, S: AsRef<str>>(
+        &self,
+        path: S,
+        token: &str,
+    ) -> Result<T, Error> {
+        self.get_with_query(path, &(), token).await
+    }
+
+    async fn get_with_query<T: DeserializeOwned, Q: Serialize, S: AsRef<str>>(
+        &self,
+        path: S,
+        query: &Q,
+        token: &str,
+    ) -> Result<T, Error> {
+        let response = self
+            .request(Method::GET, path)
+            .query(query)
+            .bearer_auth(token)
+            .send()
+            .await?;
+
+        json_or_err(response).await
+    }
+
+    pub async fn notes(&self, query: &NotesQuery, token: &str) -> Result<Vec<Note>, Error> {
+        self.get_with_query("/notes", query, token).await
+    }
+
+    /// Loads tags of each of the `notes`.
+    pub async fn with_tags(
+        &self,
+        notes: Vec<Note>,
+        token: &str,
+    ) -> Result<Vec<NoteWithTags>, Error> {
+        let tags =
+            futures::future::try_join_all(notes.iter().map(|note| self.note_tags(note.id, token)))
+                .await?;
+
+        Ok(notes.into_iter().zip(tags).collect())
+    }
+
+    pub async fn archived_notes(&self, token: &str) -> Result<Vec<Note>, Error> {
+        self.get("/notes?archived=true&sort=updated&order=desc", token)
+            .await
+    }
+
+    /// Loads notes of other users shared with the current one.
+    pub async fn shared_notes(&self, token: &str) -> Result<Vec<SharedNote>, Error> {
+        self.get("/notes/shared", token).await
+    }
+
+    /// Loads webhooks of the user along with the latest entries of their delivery logs.
+    pub async fn webhooks(
+        &self,
+        token: &str,
+    ) -> Result<Vec<(Webhook, Vec<WebhookDelivery>)>, Error> {
+        let webhooks: Vec<Webhook> = self.get("/webhooks", token).await?;
+        let deliveries = futures::future::try_join_all(webhooks.iter().map(|webhook| {
+            self.get::<Vec<WebhookDelivery>, _>(
+                format!("/webhooks/{}/deliveries", webhook.id),
+                token,
+            )
+        }))
+        .await?;
+
+        Ok(webhooks.into_iter().zip(deliveries).collect())
+    }
+
+    pub async fn note(&self, id: i32, token: &str) -> Result<Note, Error> {
+        self.get(format!("/notes/{}", id), token).await
+    }
+
+    pub async fn note_tags(&self, id: i32, token: &str) -> Result<Vec<Tag>, Error> {
+        self.get(format!("/notes/{}/tags", id), token).await
+    }
+
+    /// Loads notes linked from the content of the note `id`.
+    pub async fn note_links(&self, id: i32, token: &str) -> Result<Vec<Note>, Error> {
+        self.get(format!("/notes/{}/links", id), token).await
+    }
+
+    pub async fn note_backlinks(&self, id: i32, token: &str) -> Result<Vec<Note>, Error> {
+        self.get(format!("/notes/{}/backlinks", id), token).await
+    }
+
+    pub async fn note_tasks(&self, id: i32, token: &str) -> Result<Vec<NoteTask>, Error> {
+        self.get(format!("/notes/{}/tasks", id), token).await
+    }
+
+    /// Loads unfinished tasks of all active notes of the user.
+    pub async fn open_tasks(&self, token: &str) -> Result<Vec<NoteTask>, Error> {
+        self.get("/tasks?done=false", token).await
+    }
+
+    pub async fn upcoming_reminders(&self, token: &str) -> Result<Vec<Reminder>, Error> {
+        self.get("/reminders/upcoming", token).await
+    }
+
+    /// Loads the calendar feed of the user, `None` when it wasn't created.
+    pub async fn calendar_feed(&self, token: &str) -> Result<Option<CalendarFeed>, Error> {
+        match self.get("/calendar", token).await {
+            Ok(feed) => Ok(Some(feed)),
+            Err(Error::ApiError(StatusCode::NOT_FOUND, _)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn note_attachments(&self, id: i32, token: &str) -> Result<Vec<Attachment>, Error> {
+        self.get(format!("/notes/{}/attachments", id), token).await
+    }
+
+    pub async fn note_shares(&self, id: i32, token: &str) -> Result<Vec<NoteShare>, Error> {
+        self.get(format!("/notes/{}/shares", id), token).await
+    }
+
+    pub async fn public_links(&self, id: i32, token: &str) -> Result<Vec<PublicLink>, Error> {
+        self.get(format!("/notes/{}/public-links", id), token).await
+    }
+
+    /// Loads the note of a public link, this doesn't need a logged in user. Fails with
+    /// `PasswordRequired` when the link is protected and `password` is missing or wrong.
+    pub async fn public_note(
+        &self,
+        link_token: &str,
+        password: Option<String>,
+    ) -> Result<PublicNote, Error> {
+        let path = format!("/public/{}", link_token);
+        let request = match password {
+            Some(password) => self
+                .request(Method::POST, path)
+                .json(&LinkPassword { password }),
+            None => self.request(Method::GET, path),
+        };
+        let response = request.send().await?;
+
+        if response.status() == StatusCode::FORBIDDEN {
+            Err(Error::PasswordRequired)
+        } else {
+            json_or_err(response).await
+        }
+    }
+
+    /// Opens the websocket of the live editing session of the note `id`.
+    pub async fn live(&self, id: i32, token: &str) -> Result<LiveSocket, Error> {
+        let url = self
+            .endpoint(format!("/notes/{}/live", id))
+            .replacen("http", "ws", 1);
+        let request = Request::builder()
+            .uri(url)
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .body(())?;
+
+        match connect_async(request).await {
+            Ok((socket, _)) => Ok(socket),
+            Err(tungstenite::Error::Http(response)) => match response.status() {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Error::Unauthenticated),
+                status => Err(Error::ApiError(status, status.to_string())),
+            },
+            Err(e) => Err(Error::LiveError(e.to_string())),
+        }
+    }
+
+    pub async fn tag(&self, id: i32, token: &str) -> Result<Tag, Error> {
+        self.get(format!("/tags/{}", id), token).await
+    }
+
+    pub async fn tag_notes(&self, id: i32, token: &str) -> Result<Vec<Note>, Error> {
+        self.get(format!("/notes?tag_id={}", id), token).await
+    }
+
+    pub async fn notebooks(&self, token: &str) -> Result<Vec<Notebook>, Error> {
+        self.get("/notebooks", token).await
+    }
+
+    pub async fn saved_searches(&self, token: &str) -> Result<Vec<SavedSearch>, Error> {
+        self.get("/saved-searches", token).await
+    }
+
+    pub async fn saved_search_notes(&self, id: i32, token: &str) -> Result<Vec<Note>, Error> {
+        self.get(format!("/saved-searches/{}/notes", id), token)
+            .await
+    }
+
+    pub async fn graph(&self, query: &GraphQuery, token: &str) -> Result<Graph, Error> {
+        self.get_with_query("/graph", query, token).await
+    }
+
+    pub async fn settings(&self, token: &str) -> Result<UserSettings, Error> {
+        self.get("/settings", token).await
+    }
+}
+
+async fn json_or_err<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+    match response.status() {
+        status if status.is_success() => response.json().await.map_err(Error::from),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Error::Unauthenticated),
+        status => {
+            let message = response
+                .json::<ErrReply>()
+                .await
+                .map(|reply| reply.message)
+                .unwrap_or_else(|_| status.to_string());
+            Err(Error::ApiError(status, message))
+        }
+    }
+}
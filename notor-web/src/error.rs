@@ -0,0 +1,22 @@
+use thiserror::Error;
+use warp::http::StatusCode;
+
+#[derive(Error, Debug)]
+pub enum WebError {
+    #[error("request to notor api failed - `{0}`")]
+    ApiRequestError(#[from] reqwest::Error),
+    #[error("{1}")]
+    ApiError(StatusCode, String),
+    #[error("user is not logged in")]
+    Unauthenticated,
+    #[error("the link is protected by a password")]
+    PasswordRequired,
+    #[error("failed to render template - `{0}`")]
+    RenderError(#[from] sailfish::runtime::RenderError),
+    #[error("failed to serialize page data - `{0}`")]
+    SerializeError(#[from] serde_json::Error),
+    #[error("live editing connection failed - `{0}`")]
+    LiveError(String),
+    #[error("internal error - `{0}`")]
+    HttpError(#[from] warp::http::Error),
+}
@@ -0,0 +1,466 @@
+use futures::{SinkExt, StreamExt, TryStreamExt};
+use std::convert::Infallible;
+use tokio_tungstenite::tungstenite::Message as LiveMessage;
+use warp::{
+    http::{
+        header::{HeaderMap, CONNECTION, HOST, TRANSFER_ENCODING},
+        Method, Response, StatusCode, Uri,
+    },
+    hyper::{body::Bytes, Body},
+    path::FullPath,
+    redirect, reply,
+    ws::{Message, WebSocket, Ws},
+    Reply,
+};
+
+use crate::api::{ApiClient, GraphQuery, LiveSocket, NotesQuery};
+use crate::i18n::{I18n, Locale};
+use crate::templates::*;
+use crate::Error;
+use notor_core::models::{notebook_tree, ErrReply, LinkPassword, SharePermission, UserSettings};
+use sailfish::TemplateOnce;
+
+type PageResponse = Result<warp::reply::Response, Infallible>;
+
+const LOGIN_PAGE: &str = "/web/login";
+
+fn token_or_login(token: Option<String>) -> Result<String, Error> {
+    token.ok_or(Error::Unauthenticated)
+}
+
+/// Turns a rendered page into a response. A missing or rejected token redirects
+/// the user to the login page, any other error is displayed on an error page.
+fn page_response(page: Result<String, Error>, i18n: &I18n) -> PageResponse {
+    Ok(match page {
+        Ok(html) => reply::html(html).into_response(),
+        Err(Error::Unauthenticated) => {
+            redirect::temporary(Uri::from_static(LOGIN_PAGE)).into_response()
+        }
+        Err(err) => {
+            let status = match &err {
+                Error::ApiError(status, _) => *status,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            let message = err.to_string();
+            match error_page(message.clone(), i18n.locale(None)) {
+                Ok(html) => reply::with_status(reply::html(html), status).into_response(),
+                Err(_) => reply::with_status(message, status).into_response(),
+            }
+        }
+    })
+}
+
+fn error_page(message: String, t: &Locale) -> Result<String, Error> {
+    let body = NotFoundTmpl { message }.render_once()?;
+    render_page(t.msg("page-title"), body, &UserSettings::default(), t)
+}
+
+pub(crate) async fn index(
+    query: NotesQuery,
+    token: Option<String>,
+    i18n: I18n,
+    api: ApiClient,
+) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let notes = match query.saved_search {
+                Some(id) => api.saved_search_notes(id, &token).await?,
+                None => api.notes(&query, &token).await?,
+            };
+            let body = IndexTmpl {
+                t,
+                tz: settings.tz(),
+                notes: api.with_tags(notes, &token).await?,
+                notebooks: notebook_tree(api.notebooks(&token).await?),
+                saved_searches: api.saved_searches(&token).await?,
+                query: &query,
+                settings: &settings,
+                locales: i18n.catalog.locales(),
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn note(
+    id: i32,
+    token: Option<String>,
+    i18n: I18n,
+    api: ApiClient,
+) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let note = api.note(id, &token).await?;
+            let title = note.title.clone();
+            let (permission, shares, public_links) = if note.user_id == settings.user_id {
+                (
+                    None,
+                    api.note_shares(id, &token).await?,
+                    api.public_links(id, &token).await?,
+                )
+            } else {
+                // archived notes are missing from the listing, they can be viewed at most
+                let permission = api
+                    .shared_notes(&token)
+                    .await?
+                    .into_iter()
+                    .find(|shared| shared.note.id == id)
+                    .map(|shared| shared.permission)
+                    .unwrap_or(SharePermission::Viewer);
+                (Some(permission), vec![], vec![])
+            };
+            let body = NoteTmpl {
+                t,
+                tz: settings.tz(),
+                note,
+                note_tags: api.note_tags(id, &token).await?,
+                links: api.note_links(id, &token).await?,
+                backlinks: api.note_backlinks(id, &token).await?,
+                attachments: api.note_attachments(id, &token).await?,
+                tasks: api.note_tasks(id, &token).await?,
+                permission,
+                shares,
+                public_links,
+            }
+            .render_once()?;
+
+            render_page(title, body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn tag(
+    id: i32,
+    token: Option<String>,
+    i18n: I18n,
+    api: ApiClient,
+) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let tag = api.tag(id, &token).await?;
+            let title = t.msg_with("page-title-tag", "tag", tag.name.as_str());
+            let body = TagViewTmpl {
+                t,
+                tz: settings.tz(),
+                notes: api.tag_notes(id, &token).await?,
+                tag,
+            }
+            .render_once()?;
+
+            render_page(title, body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn archive(token: Option<String>, i18n: I18n, api: ApiClient) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let body = ArchiveTmpl {
+                t,
+                tz: settings.tz(),
+                notes: api.archived_notes(&token).await?,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-archive"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn tasks(token: Option<String>, i18n: I18n, api: ApiClient) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let body = TasksTmpl {
+                t,
+                tasks: api.open_tasks(&token).await?,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-tasks"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn reminders(token: Option<String>, i18n: I18n, api: ApiClient) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let body = RemindersTmpl {
+                t,
+                tz: settings.tz(),
+                reminders: api.upcoming_reminders(&token).await?,
+                feed: api.calendar_feed(&token).await?,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-reminders"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn webhooks(token: Option<String>, i18n: I18n, api: ApiClient) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let body = WebhooksTmpl {
+                t,
+                tz: settings.tz(),
+                webhooks: api.webhooks(&token).await?,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-webhooks"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn shared(token: Option<String>, i18n: I18n, api: ApiClient) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let body = SharedTmpl {
+                t,
+                tz: settings.tz(),
+                user_id: settings.user_id,
+                notes: api.shared_notes(&token).await?,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-shared"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn graph(
+    query: GraphQuery,
+    token: Option<String>,
+    i18n: I18n,
+    api: ApiClient,
+) -> PageResponse {
+    page_response(
+        async {
+            let token = token_or_login(token)?;
+            let settings = api.settings(&token).await?;
+            let t = i18n.locale(settings.language.as_deref());
+            let graph = api.graph(&query, &token).await?;
+            let body = GraphTmpl {
+                t,
+                graph: serde_json::to_string(&graph)?,
+                query: &query,
+            }
+            .render_once()?;
+
+            render_page(t.msg("page-title-graph"), body, &settings, t)
+        }
+        .await,
+        &i18n,
+    )
+}
+
+/// Shows the note of a public link to anyone, `password` is the submitted password
+/// form of a protected link.
+pub(crate) async fn public_note(
+    link_token: String,
+    password: Option<LinkPassword>,
+    i18n: I18n,
+    api: ApiClient,
+) -> PageResponse {
+    let t = i18n.locale(None);
+    let settings = UserSettings::default();
+    let submitted = password.is_some();
+
+    page_response(
+        async {
+            match api
+                .public_note(&link_token, password.map(|form| form.password))
+                .await
+            {
+                Ok(note) => {
+                    let title = note.title.clone();
+                    let body = PublicNoteTmpl {
+                        t,
+                        tz: settings.tz(),
+                        note,
+                    }
+                    .render_once()?;
+                    render_page(title, body, &settings, t)
+                }
+                Err(Error::PasswordRequired) => {
+                    let err = if submitted {
+                        t.msg("link-password-invalid")
+                    } else {
+                        String::new()
+                    };
+                    let body = LinkPasswordTmpl { t, err }.render_once()?;
+                    render_page(t.msg("page-title-link-password"), body, &settings, t)
+                }
+                Err(err) => Err(err),
+            }
+        }
+        .await,
+        &i18n,
+    )
+}
+
+pub(crate) async fn login(i18n: I18n) -> PageResponse {
+    let t = i18n.locale(None);
+    page_response(
+        LoginTmpl {
+            t,
+            err: String::new(),
+        }
+        .render_once()
+        .map_err(Error::from)
+        .and_then(|body| render_page(t.msg("page-title-login"), body, &UserSettings::default(), t)),
+        &i18n,
+    )
+}
+
+/// Connects the websocket of a live editing session through to the notor api, `proxy`
+/// can't forward upgraded connections.
+pub(crate) async fn live(id: i32, ws: Ws, token: Option<String>, api: ApiClient) -> PageResponse {
+    let upstream = async { api.live(id, &token_or_login(token)?).await }.await;
+
+    Ok(match upstream {
+        Ok(upstream) => ws
+            .on_upgrade(move |socket| pipe_live(socket, upstream))
+            .into_response(),
+        Err(err) => {
+            let status = match &err {
+                Error::ApiError(status, _) => *status,
+                Error::Unauthenticated => StatusCode::UNAUTHORIZED,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            reply::with_status(err.to_string(), status).into_response()
+        }
+    })
+}
+
+/// Passes text messages both ways until both sides close, that's all live editing uses.
+async fn pipe_live(socket: WebSocket, upstream: LiveSocket) {
+    let (mut to_browser, mut from_browser) = socket.split();
+    let (mut to_api, mut from_api) = upstream.split();
+
+    let browser_to_api = async {
+        while let Some(Ok(message)) = from_browser.next().await {
+            if message.is_close() {
+                break;
+            }
+            if let Ok(text) = message.to_str() {
+                if to_api.send(LiveMessage::text(text)).await.is_err() {
+                    break;
+                }
+            }
+        }
+        let _ = to_api.close().await;
+    };
+    let api_to_browser = async {
+        while let Some(Ok(message)) = from_api.next().await {
+            let text = match message {
+                LiveMessage::Text(text) => text,
+                LiveMessage::Close(_) => break,
+                _ => continue,
+            };
+            if to_browser.send(Message::text(text)).await.is_err() {
+                break;
+            }
+        }
+        let _ = to_browser.close().await;
+    };
+
+    // closing either side makes the other close as well
+    futures::future::join(browser_to_api, api_to_browser).await;
+}
+
+/// Forwards a request that is not handled by the web interface to the notor api
+/// so that the browser can talk to both through a single origin.
+pub(crate) async fn proxy(
+    method: Method,
+    path: FullPath,
+    query: String,
+    mut headers: HeaderMap,
+    body: Bytes,
+    api: ApiClient,
+) -> PageResponse {
+    let mut endpoint = path.as_str().to_string();
+    if !query.is_empty() {
+        endpoint.push('?');
+        endpoint.push_str(&query);
+    }
+    headers.remove(HOST);
+
+    let response = match api
+        .request(method, endpoint)
+        .headers(headers)
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            return Ok(reply::with_status(
+                reply::json(&ErrReply::new(Error::from(err).to_string())),
+                StatusCode::BAD_GATEWAY,
+            )
+            .into_response())
+        }
+    };
+
+    let mut builder = Response::builder().status(response.status());
+    for (name, value) in response.headers() {
+        if name != CONNECTION && name != TRANSFER_ENCODING {
+            builder = builder.header(name, value);
+        }
+    }
+
+    Ok(builder
+        .body(Body::wrap_stream(
+            response.bytes_stream().map_err(Error::from),
+        ))
+        .unwrap_or_else(|err| {
+            reply::with_status(
+                reply::json(&ErrReply::new(Error::from(err).to_string())),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            )
+            .into_response()
+        }))
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+use std::sync::Arc;
+use unic_langid::LanguageIdentifier;
+
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../i18n/en.ftl")),
+    ("pl", include_str!("../i18n/pl.ftl")),
+];
+const DEFAULT_LANGUAGE: &str = "en";
+const DATETIME_FORMAT_MSG: &str = "datetime-format";
+/// Older edits are described with a date instead of the number of days
+const EDITED_DAYS_AGO_MAX: i64 = 30;
+
+/// Translated messages of a single language.
+pub struct Locale {
+    lang: LanguageIdentifier,
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Locale {
+    fn new(lang: &str, source: &str) -> Self {
+        let lang: LanguageIdentifier = lang.parse().expect("invalid catalog language");
+        let resource = FluentResource::try_new(source.to_string())
+            .unwrap_or_else(|(_, errs)| panic!("invalid `{}` catalog - {:?}", lang, errs));
+
+        let mut bundle = FluentBundle::new_concurrent(vec![lang.clone()]);
+        // messages are rendered into html, unicode isolation marks would only get in the way
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(resource)
+            .unwrap_or_else(|errs| panic!("invalid `{}` catalog - {:?}", lang, errs));
+
+        Locale { lang, bundle }
+    }
+
+    pub fn lang(&self) -> String {
+        self.lang.to_string()
+    }
+
+    /// Returns the message `id` or the id itself if the catalog doesn't define it.
+    pub fn msg(&self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    pub fn msg_with<'a, V: Into<FluentValue<'a>>>(&self, id: &str, arg: &str, value: V) -> String {
+        let mut args = FluentArgs::new();
+        args.set(arg, value.into());
+        self.format(id, Some(&args))
+    }
+
+    /// Formats `datetime` as seen in the time zone `tz` of the viewer.
+    pub fn datetime(&self, datetime: &DateTime<Utc>, tz: &Tz) -> String {
+        datetime
+            .with_timezone(tz)
+            .format(&self.msg(DATETIME_FORMAT_MSG))
+            .to_string()
+    }
+
+    /// Describes how long ago a note was last edited.
+    pub fn edited(&self, updated: &DateTime<Utc>, tz: &Tz) -> String {
+        let elapsed = Utc::now().signed_duration_since(*updated);
+
+        if elapsed.num_minutes() < 1 {
+            self.msg("edited-just-now")
+        } else if elapsed.num_hours() < 1 {
+            self.msg_with("edited-minutes-ago", "count", elapsed.num_minutes())
+        } else if elapsed.num_days() < 1 {
+            self.msg_with("edited-hours-ago", "count", elapsed.num_hours())
+        } else if elapsed.num_days() <= EDITED_DAYS_AGO_MAX {
+            self.msg_with("edited-days-ago", "count", elapsed.num_days())
+        } else {
+            self.msg_with("edited-on", "date", self.datetime(updated, tz))
+        }
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        match self.bundle.get_message(id).and_then(|msg| msg.value()) {
+            Some(pattern) => {
+                let mut errs = vec![];
+                self.bundle
+                    .format_pattern(pattern, args, &mut errs)
+                    .into_owned()
+            }
+            None => id.to_string(),
+        }
+    }
+}
+
+/// All languages the interface is translated to.
+pub struct Catalog {
+    locales: Vec<Locale>,
+}
+
+impl Catalog {
+    pub fn load() -> Arc<Self> {
+        Arc::new(Catalog {
+            locales: CATALOGS
+                .iter()
+                .map(|(lang, source)| Locale::new(lang, source))
+                .collect(),
+        })
+    }
+
+    pub fn locales(&self) -> &[Locale] {
+        &self.locales
+    }
+
+    /// Picks the best matching locale. The language chosen by the user in the settings
+    /// takes precedence over the `Accept-Language` header sent by the browser.
+    pub fn negotiate(&self, preferred: Option<&str>, accept_language: Option<&str>) -> &Locale {
+        let mut requested: Vec<LanguageIdentifier> = preferred
+            .and_then(|lang| lang.parse().ok())
+            .into_iter()
+            .collect();
+        if let Some(header) = accept_language {
+            requested.extend(accepted_languages::parse(header));
+        }
+
+        let available: Vec<&LanguageIdentifier> =
+            self.locales.iter().map(|locale| &locale.lang).collect();
+        let default = self.default_locale();
+
+        negotiate_languages(
+            &requested,
+            &available,
+            Some(&&default.lang),
+            NegotiationStrategy::Lookup,
+        )
+        .first()
+        .and_then(|lang| self.locales.iter().find(|locale| &locale.lang == **lang))
+        .unwrap_or(default)
+    }
+
+    fn default_locale(&self) -> &Locale {
+        self.locales
+            .iter()
+            .find(|locale| locale.lang.language == DEFAULT_LANGUAGE)
+            .unwrap_or(&self.locales[0])
+    }
+}
+
+/// Language related request data used to pick the locale of a page.
+#[derive(Clone)]
+pub struct I18n {
+    pub catalog: Arc<Catalog>,
+    pub accept_language: Option<String>,
+}
+
+impl I18n {
+    pub fn locale(&self, preferred: Option<&str>) -> &Locale {
+        self.catalog
+            .negotiate(preferred, self.accept_language.as_deref())
+    }
+}
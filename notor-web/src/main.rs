@@ -1,3 +1,22 @@
-fn main() {
-    println!("Hello, world!");
+mod api;
+mod error;
+mod handlers;
+mod i18n;
+mod routes;
+mod templates;
+
+use error::WebError as Error;
+
+const WEB_PORT: u16 = 3694;
+
+#[tokio::main]
+async fn main() {
+    pretty_env_logger::init();
+
+    let api = api::ApiClient::from_env();
+    let catalog = i18n::Catalog::load();
+
+    warp::serve(routes::routes(api, catalog))
+        .run(([127, 0, 0, 1], WEB_PORT))
+        .await;
 }
@@ -0,0 +1,198 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::{
+    filters::{cookie, header, header::headers_cloned, method, path::full, query},
+    Filter, Rejection, Reply,
+};
+
+use crate::api::{ApiClient, GraphQuery, NotesQuery};
+use crate::handlers::*;
+use crate::i18n::{Catalog, I18n};
+
+const BEARER_COOKIE: &str = "Bearer";
+const STATIC_DIR: &str = "./static";
+
+fn with_api(api: ApiClient) -> impl Filter<Extract = (ApiClient,), Error = Infallible> + Clone {
+    warp::any().map(move || api.clone())
+}
+
+fn with_token() -> impl Filter<Extract = (Option<String>,), Error = Infallible> + Clone {
+    cookie::optional(BEARER_COOKIE)
+}
+
+fn with_i18n(catalog: Arc<Catalog>) -> impl Filter<Extract = (I18n,), Error = Rejection> + Clone {
+    header::optional::<String>("accept-language").map(move |accept_language| I18n {
+        catalog: catalog.clone(),
+        accept_language,
+    })
+}
+
+fn ro_index(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web")
+        .and(warp::get())
+        .and(query::query::<NotesQuery>())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(index)
+}
+fn ro_login(catalog: Arc<Catalog>) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "login")
+        .and(warp::get())
+        .and(with_i18n(catalog))
+        .and_then(login)
+}
+fn ro_note(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "notes" / i32)
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(note)
+}
+fn ro_tag(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "tags" / i32)
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(tag)
+}
+fn ro_archive(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "archive")
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(archive)
+}
+fn ro_shared(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "shared")
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(shared)
+}
+fn ro_reminders(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "reminders")
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(reminders)
+}
+
+fn ro_tasks(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "tasks")
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(tasks)
+}
+fn ro_webhooks(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "webhooks")
+        .and(warp::get())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(webhooks)
+}
+fn ro_graph(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("web" / "graph")
+        .and(warp::get())
+        .and(query::query::<GraphQuery>())
+        .and(with_token())
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(graph)
+}
+
+fn ro_public_note(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("s" / String)
+        .and(
+            warp::get()
+                .map(|| None)
+                .or(warp::post().and(warp::body::form()).map(Some))
+                .unify(),
+        )
+        .and(with_i18n(catalog))
+        .and(with_api(api))
+        .and_then(public_note)
+}
+
+fn ro_live(api: ApiClient) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("notes" / i32 / "live")
+        .and(warp::ws())
+        .and(with_token())
+        .and(with_api(api))
+        .and_then(live)
+}
+
+fn ro_static() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path("static").and(warp::fs::dir(STATIC_DIR))
+}
+
+fn ro_proxy(api: ApiClient) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    method::method()
+        .and(full())
+        .and(query::raw().or(warp::any().map(String::new)).unify())
+        .and(headers_cloned())
+        .and(warp::body::bytes())
+        .and(with_api(api))
+        .and_then(proxy)
+}
+
+pub fn routes(
+    api: ApiClient,
+    catalog: Arc<Catalog>,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let web_routes = ro_index(api.clone(), catalog.clone())
+        .or(ro_login(catalog.clone()))
+        .or(ro_note(api.clone(), catalog.clone()))
+        .or(ro_tag(api.clone(), catalog.clone()))
+        .or(ro_archive(api.clone(), catalog.clone()))
+        .or(ro_shared(api.clone(), catalog.clone()))
+        .or(ro_tasks(api.clone(), catalog.clone()))
+        .or(ro_reminders(api.clone(), catalog.clone()))
+        .or(ro_webhooks(api.clone(), catalog.clone()))
+        .or(ro_public_note(api.clone(), catalog.clone()))
+        .or(ro_graph(api.clone(), catalog));
+
+    web_routes
+        .or(ro_live(api.clone()))
+        .or(ro_static())
+        .or(ro_proxy(api))
+        .with(warp::log("notor_web::routes"))
+}
@@ -0,0 +1,254 @@
+use chrono_tz::{Tz, TZ_VARIANTS};
+use sailfish::TemplateOnce;
+
+use crate::api::{GraphQuery, NotesQuery};
+use crate::i18n::Locale;
+use crate::Error;
+use notor_core::links::{content_parts, AttachmentRef, ContentPart, WikiLink};
+use notor_core::models::{
+    Attachment, CalendarFeed, Note, NoteShare, NoteTask, NoteWithTags, Notebook, PublicLink,
+    PublicNote, Reminder, SavedSearch, SharePermission, SharedNote, Tag, Theme, UserSettings,
+    Webhook, WebhookDelivery, WEBHOOK_EVENTS,
+};
+
+const STYLE_SRCS: &[&str] = &[
+    "/static/css/style.css",
+    "https://cdnjs.cloudflare.com/ajax/libs/font-awesome/5.15.2/css/all.min.css",
+];
+const SCRIPT_SRCS: &[&str] = &["/static/js/glue.js"];
+
+pub enum Charset {
+    Utf8,
+}
+
+impl AsRef<str> for Charset {
+    fn as_ref(&self) -> &str {
+        match self {
+            Charset::Utf8 => "utf-8",
+        }
+    }
+}
+
+pub struct MetaTag<'a> {
+    pub name: &'a str,
+    pub content: &'a str,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "html.stpl")]
+pub struct HtmlTmpl<'a> {
+    pub lang: &'a str,
+    pub theme: &'a str,
+    pub head: String,
+    pub body: String,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "head.stpl")]
+pub struct HeadTmpl<'a> {
+    pub title: &'a str,
+    pub charset: Charset,
+    pub meta_tags: Vec<MetaTag<'a>>,
+    pub style_srcs: Vec<&'a str>,
+    pub script_srcs: Vec<&'a str>,
+    pub scripts: Vec<&'a str>,
+    pub styles: Vec<&'a str>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "index.stpl")]
+pub struct IndexTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub notes: Vec<NoteWithTags>,
+    /// Notebooks of the user in tree order paired with their depth
+    pub notebooks: Vec<(usize, Notebook)>,
+    pub saved_searches: Vec<SavedSearch>,
+    pub query: &'a NotesQuery,
+    pub settings: &'a UserSettings,
+    pub locales: &'a [Locale],
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "note.stpl")]
+pub struct NoteTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub note: Note,
+    pub note_tags: Vec<Tag>,
+    /// Notes linked from the content
+    pub links: Vec<Note>,
+    pub backlinks: Vec<Note>,
+    pub attachments: Vec<Attachment>,
+    pub tasks: Vec<NoteTask>,
+    /// Permission of the viewer to a note shared with them, `None` for the owner
+    pub permission: Option<SharePermission>,
+    /// Users the note is shared with, only loaded for the owner
+    pub shares: Vec<NoteShare>,
+    /// Public links to the note, only loaded for the owner
+    pub public_links: Vec<PublicLink>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "public_note.stpl")]
+pub struct PublicNoteTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub note: PublicNote,
+}
+
+/// Asks visitors of a protected public link for the password.
+#[derive(TemplateOnce)]
+#[template(path = "link_password.stpl")]
+pub struct LinkPasswordTmpl<'a> {
+    pub t: &'a Locale,
+    pub err: String,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "tagview.stpl")]
+pub struct TagViewTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub tag: Tag,
+    pub notes: Vec<Note>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "archive.stpl")]
+pub struct ArchiveTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub notes: Vec<Note>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "shared.stpl")]
+pub struct SharedTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    /// Id of the viewer, used to stop sharing a note with them
+    pub user_id: i32,
+    pub notes: Vec<SharedNote>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "tasks.stpl")]
+pub struct TasksTmpl<'a> {
+    pub t: &'a Locale,
+    pub tasks: Vec<NoteTask>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "reminders.stpl")]
+pub struct RemindersTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub reminders: Vec<Reminder>,
+    pub feed: Option<CalendarFeed>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "webhooks.stpl")]
+pub struct WebhooksTmpl<'a> {
+    pub t: &'a Locale,
+    pub tz: Tz,
+    pub webhooks: Vec<(Webhook, Vec<WebhookDelivery>)>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "graph.stpl")]
+pub struct GraphTmpl<'a> {
+    pub t: &'a Locale,
+    /// Graph serialized for the script drawing it
+    pub graph: String,
+    pub query: &'a GraphQuery,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "login.stpl")]
+pub struct LoginTmpl<'a> {
+    pub t: &'a Locale,
+    pub err: String,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "404.stpl")]
+pub struct NotFoundTmpl {
+    pub message: String,
+}
+
+/// Finds the note a link in the content points to, a title linking several notes
+/// resolves to the oldest one.
+fn linked_note<'a>(links: &'a [Note], link: &WikiLink) -> Option<&'a Note> {
+    links.iter().find(|note| match link {
+        WikiLink::Id(id) => note.id == *id,
+        WikiLink::Title(title) => note.title == *title,
+    })
+}
+
+/// Finds the image attachment an image in the content shows.
+fn attached_image<'a>(
+    attachments: &'a [Attachment],
+    attachment: &AttachmentRef,
+) -> Option<&'a Attachment> {
+    attachments
+        .iter()
+        .filter(|candidate| candidate.is_image())
+        .find(|candidate| match attachment {
+            AttachmentRef::Id(id) => candidate.id == *id,
+            AttachmentRef::Name(name) => candidate.filename == *name,
+        })
+}
+
+/// Wraps a rendered `body` into a full html document applying the appearance
+/// preferences of the user.
+pub fn render_page<S: AsRef<str>>(
+    title: S,
+    body: String,
+    settings: &UserSettings,
+    t: &Locale,
+) -> Result<String, Error> {
+    let head = HeadTmpl {
+        title: title.as_ref(),
+        charset: Charset::Utf8,
+        meta_tags: vec![
+            MetaTag {
+                name: "viewport",
+                content: "width=device-width, initial-scale=1",
+            },
+            MetaTag {
+                name: "color-scheme",
+                content: color_scheme(settings.theme),
+            },
+        ],
+        style_srcs: STYLE_SRCS.to_vec(),
+        script_srcs: SCRIPT_SRCS.to_vec(),
+        scripts: vec![],
+        styles: vec![],
+    }
+    .render_once()?;
+
+    let lang = t.lang();
+    HtmlTmpl {
+        lang: &lang,
+        theme: settings.theme.as_ref(),
+        head,
+        body,
+    }
+    .render_once()
+    .map_err(Error::from)
+}
+
+fn color_scheme(theme: Theme) -> &'static str {
+    match theme {
+        Theme::Auto => "light dark",
+        Theme::Light => "light",
+        Theme::Dark | Theme::HighContrast => "dark",
+    }
+}
+
+pub const THEMES: &[Theme] = &[Theme::Auto, Theme::Light, Theme::Dark, Theme::HighContrast];
+
+pub const SHARE_PERMISSIONS: &[SharePermission] =
+    &[SharePermission::Viewer, SharePermission::Editor];
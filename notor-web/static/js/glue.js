@@ -1,5 +1,7 @@
 const BEARER = 'Bearer';
 const TOKEN_EXPIRATION_MINUTES = 5;
+// Changes often come in bursts, like tagging a note creating the tag as well
+const RELOAD_DELAY_MS = 300;
 
 function getBack() {
     window.history.back();
@@ -68,20 +70,88 @@ async function deleteNote(id) {
     await displayErrOrReload(response);
 }
 
+async function archiveNote(id, archive) {
+    const response = await request("POST", "/notes/" + id + (archive ? "/archive" : "/unarchive"));
+    await displayErrOrReload(response);
+}
+
+// `flag` is either "pin" or "favorite"
+async function toggleNoteFlag(id, flag, isSet) {
+    const response = await request(isSet ? "DELETE" : "POST", "/notes/" + id + "/" + flag);
+    await displayErrOrReload(response);
+}
+
 async function addNewNote(event) {
     event.preventDefault();
     const data = new FormData(event.target);
 
+    const notebook = data.get("notebook_id");
     note = {
-        username: getCookie("Username"),
         title: data.get("title"),
         content: data.get("content"),
+        notebook_id: notebook ? parseInt(notebook) : null,
     };
 
     const response = await request("PUT", "/notes", body = note, json = true);
     await displayErrOrReload(response);
 }
 
+async function addNotebook(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    const parent = data.get("parent_id");
+    notebook = {
+        name: data.get("name"),
+        parent_id: parent ? parseInt(parent) : null,
+    };
+
+    const response = await request("PUT", "/notebooks", body = notebook, json = true);
+    await displayErrOrReload(response);
+}
+
+async function deleteNotebook(id) {
+    const response = await request("DELETE", "/notebooks/" + id);
+    if (response.status !== 200) {
+        await displayErrOrReload(response);
+    } else {
+        goHome();
+    }
+}
+
+const SEARCH_PARAMS = ["q", "tags", "notebook_id", "sort", "order"];
+
+async function saveSearch(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    const params = new URLSearchParams(window.location.search);
+    var query = {};
+    for (const param of SEARCH_PARAMS) {
+        const value = params.get(param);
+        if (value) {
+            query[param] = param === "notebook_id" ? parseInt(value) : value;
+        }
+    }
+
+    search = {
+        name: data.get("name"),
+        query: query,
+    };
+
+    const response = await request("PUT", "/saved-searches", body = search, json = true);
+    await displayErrOrReload(response);
+}
+
+async function deleteSavedSearch(id) {
+    const response = await request("DELETE", "/saved-searches/" + id);
+    if (response.status !== 200) {
+        await displayErrOrReload(response);
+    } else {
+        goHome();
+    }
+}
+
 async function tagNote(event) {
     event.preventDefault();
     const data = new FormData(event.target);
@@ -92,7 +162,162 @@ async function tagNote(event) {
     const note_id = results[1];
     const tag = data.get("tag");
 
-    const response = await request("POST", "/notes/" + note_id + "/tags/" + tag);
+    const response = await request("POST", "/notes/" + note_id + "/tags/" + encodeURIComponent(tag));
+    await displayErrOrReload(response);
+}
+
+async function uploadAttachments(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    const response = await request("POST", "/notes/" + event.target.dataset.note + "/attachments", body = data);
+    await displayErrOrReload(response);
+}
+
+async function deleteAttachment(id) {
+    const response = await request("DELETE", "/attachments/" + id);
+    await displayErrOrReload(response);
+}
+
+async function shareNote(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    share = {
+        username: data.get("username"),
+        permission: data.get("permission"),
+    };
+
+    const response = await request("PUT", "/notes/" + event.target.dataset.note + "/shares", body = share, json = true);
+    await displayErrOrReload(response);
+}
+
+async function unshareNote(noteId, userId) {
+    const response = await request("DELETE", "/notes/" + noteId + "/shares/" + userId);
+    await displayErrOrReload(response);
+}
+
+async function createPublicLink(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    // `datetime-local` inputs hold the local time of the browser
+    const expires = data.get("expires");
+    const password = data.get("password");
+    link = {
+        expires: expires ? new Date(expires).toISOString() : null,
+        password: password ? password : null,
+    };
+
+    const response = await request("PUT", "/notes/" + event.target.dataset.note + "/public-links", body = link, json = true);
+    await displayErrOrReload(response);
+}
+
+async function revokePublicLink(id) {
+    const response = await request("DELETE", "/public-links/" + id);
+    await displayErrOrReload(response);
+}
+
+async function toggleTask(noteId, position) {
+    const response = await request("POST", "/notes/" + noteId + "/tasks/" + position + "/toggle");
+    await displayErrOrReload(response);
+}
+
+async function saveReminder(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    // `datetime-local` inputs hold the local time of the browser, empty ones are left as they are
+    const patch = {};
+    for (const field of ["due_at", "remind_at"]) {
+        const value = data.get(field);
+        if (value) {
+            patch[field] = new Date(value).toISOString();
+        }
+    }
+    if (Object.keys(patch).length > 0) {
+        await patchReminder(event.target.dataset.note, patch);
+    }
+}
+
+async function patchReminder(noteId, patch) {
+    const response = await request("PATCH", "/notes/" + noteId, body = patch, json = true);
+    await displayErrOrReload(response);
+}
+
+async function snoozeReminder(noteId, minutes) {
+    const response = await request("POST", "/notes/" + noteId + "/reminder/snooze", body = { minutes: minutes }, json = true);
+    await displayErrOrReload(response);
+}
+
+// Shows a due reminder on top of the page and as a system notification if the user allows them
+async function showReminder(noteId) {
+    const response = await request("GET", "/notes/" + noteId);
+    if (response.status !== 200) {
+        return;
+    }
+    const note = await response.json();
+
+    const reminder = document.createElement("a");
+    reminder.className = "reminder-toast";
+    reminder.href = "/web/notes/" + noteId;
+    reminder.innerHTML = '<i class="fas fa-bell"></i> ';
+    reminder.append(note.title);
+    document.body.append(reminder);
+
+    if ("Notification" in window && Notification.permission === "granted") {
+        new Notification(note.title);
+    }
+}
+
+async function addWebhook(event) {
+    event.preventDefault();
+    const data = new FormData(event.target);
+
+    const tag = data.get("tag");
+    webhook = {
+        url: data.get("url"),
+        events: data.getAll("events"),
+        tag: tag ? tag : null,
+    };
+
+    const response = await request("PUT", "/webhooks", body = webhook, json = true);
+    await displayErrOrReload(response);
+}
+
+async function testWebhook(id) {
+    const response = await request("POST", "/webhooks/" + id + "/test");
+    await displayErrOrReload(response);
+}
+
+async function createCalendarFeed() {
+    const response = await request("PUT", "/calendar");
+    await displayErrOrReload(response);
+}
+
+async function deleteCalendarFeed() {
+    const response = await request("DELETE", "/calendar");
+    await displayErrOrReload(response);
+}
+
+async function deleteWebhook(id) {
+    const response = await request("DELETE", "/webhooks/" + id);
+    await displayErrOrReload(response);
+}
+
+async function saveSettings(event) {
+    const theme = document.querySelector("#theme_select").value;
+    const language = document.querySelector("#language_select").value;
+    const timezone = document.querySelector("#timezone_select").value;
+    document.documentElement.dataset.theme = theme;
+
+    settings = {
+        theme: theme,
+        language: language.length > 0 ? language : null,
+        timezone: timezone,
+    };
+
+    const response = await request("POST", "/settings", body = settings, json = true);
     await displayErrOrReload(response);
 }
 
@@ -120,17 +345,362 @@ async function handleHref(event) {
     console.log(event);
 }
 
+const SVG_NS = "http://www.w3.org/2000/svg";
+const GRAPH_ITERATIONS = 300;
+
+// Lays the graph out with a simple force simulation and draws it into `svg`
+function drawGraph(svg) {
+    const graph = JSON.parse(svg.dataset.graph);
+    const root = svg.dataset.root ? parseInt(svg.dataset.root) : null;
+    const width = svg.clientWidth;
+    const height = svg.clientHeight;
+    svg.setAttribute("viewBox", "0 0 " + width + " " + height);
+
+    const key = (kind, id) => kind + ":" + id;
+    var nodes = {};
+    graph.nodes.forEach(function(node, i) {
+        const angle = 2 * Math.PI * i / graph.nodes.length;
+        node.x = width / 2 + Math.cos(angle) * width / 3;
+        node.y = height / 2 + Math.sin(angle) * height / 3;
+        nodes[key(node.kind, node.id)] = node;
+    });
+    const edges = graph.edges.map(function(edge) {
+        if (edge.kind === "link") {
+            return [nodes[key("note", edge.source)], nodes[key("note", edge.target)], edge.kind];
+        }
+        return [nodes[key("note", edge.note_id)], nodes[key("tag", edge.tag_id)], edge.kind];
+    });
+
+    const all = Object.values(nodes);
+    const spacing = Math.sqrt(width * height / Math.max(all.length, 1));
+    for (var step = 0; step < GRAPH_ITERATIONS; step++) {
+        const cooling = 1 - step / GRAPH_ITERATIONS;
+        all.forEach(function(node) { node.dx = 0; node.dy = 0; });
+        for (var i = 0; i < all.length; i++) {
+            for (var j = i + 1; j < all.length; j++) {
+                const dx = all[i].x - all[j].x;
+                const dy = all[i].y - all[j].y;
+                const dist = Math.max(Math.hypot(dx, dy), 1);
+                const force = spacing * spacing / dist / dist;
+                all[i].dx += dx * force; all[i].dy += dy * force;
+                all[j].dx -= dx * force; all[j].dy -= dy * force;
+            }
+        }
+        edges.forEach(function([a, b]) {
+            const dx = a.x - b.x;
+            const dy = a.y - b.y;
+            const dist = Math.max(Math.hypot(dx, dy), 1);
+            const force = dist / spacing;
+            a.dx -= dx * force; a.dy -= dy * force;
+            b.dx += dx * force; b.dy += dy * force;
+        });
+        all.forEach(function(node) {
+            const len = Math.max(Math.hypot(node.dx, node.dy), 1);
+            const move = Math.min(len, spacing * cooling);
+            node.x = Math.min(width - 20, Math.max(20, node.x + node.dx / len * move));
+            node.y = Math.min(height - 20, Math.max(20, node.y + node.dy / len * move));
+        });
+    }
+
+    edges.forEach(function([a, b, kind]) {
+        const line = document.createElementNS(SVG_NS, "line");
+        line.setAttribute("x1", a.x); line.setAttribute("y1", a.y);
+        line.setAttribute("x2", b.x); line.setAttribute("y2", b.y);
+        line.setAttribute("class", "graph-edge " + kind);
+        svg.appendChild(line);
+    });
+    all.forEach(function(node) {
+        const group = document.createElementNS(SVG_NS, "a");
+        group.setAttribute("class", "graph-node " + node.kind + (node.kind === "note" && node.id === root ? " root" : ""));
+        group.setAttribute("href", node.kind === "note" ? "/web/graph?note_id=" + node.id + "&depth=2" : "/web/tags/" + node.id);
+
+        const circle = document.createElementNS(SVG_NS, "circle");
+        circle.setAttribute("cx", node.x); circle.setAttribute("cy", node.y);
+        circle.setAttribute("r", node.kind === "note" ? 8 : 6);
+        if (node.color) {
+            circle.style.fill = node.color;
+        }
+        const label = document.createElementNS(SVG_NS, "text");
+        label.setAttribute("x", node.x + 10); label.setAttribute("y", node.y + 4);
+        label.textContent = node.kind === "note" ? node.title : "#" + node.name;
+
+        group.appendChild(circle);
+        group.appendChild(label);
+        svg.appendChild(group);
+    });
+}
+
+// Ids of characters are `[counter, site]` pairs, see `notor_core::crdt`
+function compareIds(a, b) {
+    if (a[0] !== b[0]) {
+        return a[0] < b[0] ? -1 : 1;
+    }
+    if (a[1] !== b[1]) {
+        return a[1] < b[1] ? -1 : 1;
+    }
+    return 0;
+}
+
+// Same replicated growable array as `TextDoc` of the server so both end up with the same text
+class TextDoc {
+    constructor(items, clock) {
+        this.items = items;
+        this.clock = clock;
+    }
+
+    position(id) {
+        return this.items.findIndex(item => compareIds(item.id, id) === 0);
+    }
+
+    visible() {
+        return this.items.filter(item => !item.deleted);
+    }
+
+    text() {
+        return this.visible().map(item => item.ch).join("");
+    }
+
+    apply(op) {
+        if (op.kind === "insert") {
+            if (this.position(op.id) >= 0) {
+                return;
+            }
+            var i = op.origin === null ? 0 : this.position(op.origin) + 1;
+            while (i < this.items.length && compareIds(this.items[i].id, op.id) > 0) {
+                i++;
+            }
+            this.items.splice(i, 0, { id: op.id, ch: op.ch, deleted: false });
+            this.clock = Math.max(this.clock, op.id[0]);
+        } else {
+            var i = this.position(op.id);
+            if (i >= 0) {
+                this.items[i].deleted = true;
+            }
+        }
+    }
+
+    // Id of the visible character before `index` which stays put when others edit the text
+    anchor(index) {
+        const visible = this.visible();
+        return index > 0 ? visible[index - 1].id : null;
+    }
+
+    indexAfter(anchor) {
+        if (anchor === null) {
+            return 0;
+        }
+        var index = 0;
+        for (const item of this.items) {
+            if (!item.deleted) {
+                index++;
+            }
+            if (compareIds(item.id, anchor) === 0) {
+                break;
+            }
+        }
+        return index;
+    }
+}
+
+// Textareas count UTF-16 code units while documents count characters
+function charIndex(text, offset) {
+    return Array.from(text.slice(0, offset)).length;
+}
+
+function textOffset(text, index) {
+    return Array.from(text).slice(0, index).join("").length;
+}
+
+// Turns the change of the textarea into edits of the document, the changed part is found
+// by comparing the old and new text from both ends
+function localEdit(live, textarea) {
+    const visible = live.doc.visible();
+    const chars = Array.from(textarea.value);
+
+    var start = 0;
+    while (start < visible.length && start < chars.length && visible[start].ch === chars[start]) {
+        start++;
+    }
+    var end = 0;
+    while (end < visible.length - start && end < chars.length - start
+        && visible[visible.length - 1 - end].ch === chars[chars.length - 1 - end]) {
+        end++;
+    }
+
+    const ops = [];
+    for (var i = start; i < visible.length - end; i++) {
+        ops.push({ kind: "delete", id: visible[i].id });
+    }
+    var origin = start > 0 ? visible[start - 1].id : null;
+    for (const ch of chars.slice(start, chars.length - end)) {
+        const id = [++live.doc.clock, live.site];
+        ops.push({ kind: "insert", id: id, origin: origin, ch: ch });
+        origin = id;
+    }
+    ops.forEach(op => live.doc.apply(op));
+
+    if (ops.length > 0) {
+        live.socket.send(JSON.stringify({ type: "ops", ops: ops }));
+    }
+}
+
+function remoteEdit(live, textarea, ops) {
+    const text = textarea.value;
+    const selectionStart = live.doc.anchor(charIndex(text, textarea.selectionStart));
+    const selectionEnd = live.doc.anchor(charIndex(text, textarea.selectionEnd));
+
+    ops.forEach(op => live.doc.apply(op));
+
+    const newText = live.doc.text();
+    textarea.value = newText;
+    textarea.setSelectionRange(
+        textOffset(newText, live.doc.indexAfter(selectionStart)),
+        textOffset(newText, live.doc.indexAfter(selectionEnd)),
+    );
+}
+
+function showPresence(users) {
+    const presence = document.querySelector("#presence");
+    presence.replaceChildren(...users.map(function(username) {
+        const user = document.createElement("span");
+        user.className = "presence-user";
+        user.title = username;
+        user.innerText = username;
+        return user;
+    }));
+}
+
+// Opens the live editing session of the note, edits of everyone with the note open are
+// merged by the server and show up in the textarea as they're made
+function connectLive(textarea) {
+    const scheme = location.protocol === "https:" ? "wss://" : "ws://";
+    const socket = new WebSocket(scheme + location.host + "/notes/" + textarea.dataset.note + "/live");
+    const live = { socket: socket, doc: null, site: null, replaced: false };
+    textarea.readOnly = true;
+
+    socket.addEventListener("message", function(event) {
+        const message = JSON.parse(event.data);
+        switch (message.type) {
+            case "init":
+                live.doc = new TextDoc(message.items, message.clock);
+                live.site = message.site;
+                textarea.value = live.doc.text();
+                textarea.readOnly = !message.editable;
+                textarea.oninput = () => localEdit(live, textarea);
+                break;
+            case "ops":
+                remoteEdit(live, textarea, message.ops);
+                break;
+            case "presence":
+                showPresence(message.users);
+                break;
+            case "error":
+                // the server kept its own state, start over from it
+                displayErr(message.message);
+                live.replaced = true;
+                socket.close();
+                connectLive(textarea);
+                break;
+        }
+    });
+    socket.addEventListener("close", function() {
+        if (!live.replaced) {
+            textarea.readOnly = true;
+        }
+    });
+}
+
+// Reloads the page when notes or tags change, also on other devices. The content of the
+// note being edited live is kept up to date by its session instead.
+function listenForChanges(liveEditor) {
+    const events = new EventSource("/events");
+    var reload = null;
+
+    events.addEventListener("change", function(event) {
+        const change = JSON.parse(event.data);
+        if (change.action === "reminder") {
+            showReminder(change.id);
+            return;
+        }
+        const current = liveEditor && change.kind === "note" && change.id == liveEditor.dataset.note;
+        if (current && change.action === "deleted") {
+            goHome();
+            return;
+        }
+        if (current && change.action === "updated") {
+            return;
+        }
+
+        clearTimeout(reload);
+        reload = setTimeout(() => location.reload(), RELOAD_DELAY_MS);
+    });
+}
+
 document.addEventListener("DOMContentLoaded", function() {
     var newNote = document.querySelector("#new_note");
     if (newNote) {
         newNote.addEventListener("submit", addNewNote);
     }
 
+    var newNotebook = document.querySelector("#new_notebook");
+    if (newNotebook) {
+        newNotebook.addEventListener("submit", addNotebook);
+    }
+
+    var saveSearchForm = document.querySelector("#save_search");
+    if (saveSearchForm) {
+        saveSearchForm.addEventListener("submit", saveSearch);
+    }
+
     var addTag = document.querySelector("#add_tag_form");
     if (addTag) {
         addTag.addEventListener("submit", tagNote);
     }
 
+    var graph = document.querySelector("#graph");
+    if (graph) {
+        drawGraph(graph);
+    }
+
+    var liveEditor = document.querySelector("#live_editor");
+    if (liveEditor) {
+        connectLive(liveEditor);
+    }
+
+    if (getCookie(BEARER)) {
+        listenForChanges(liveEditor);
+    }
+
+    var attachmentForm = document.querySelector("#attachment_form");
+    if (attachmentForm) {
+        attachmentForm.addEventListener("submit", uploadAttachments);
+    }
+
+    var shareForm = document.querySelector("#share_form");
+    if (shareForm) {
+        shareForm.addEventListener("submit", shareNote);
+    }
+
+    var reminderForm = document.querySelector("#reminder_form");
+    if (reminderForm) {
+        reminderForm.addEventListener("submit", saveReminder);
+    }
+
+    var publicLinkForm = document.querySelector("#public_link_form");
+    if (publicLinkForm) {
+        publicLinkForm.addEventListener("submit", createPublicLink);
+    }
+
+    var newWebhook = document.querySelector("#new_webhook");
+    if (newWebhook) {
+        newWebhook.addEventListener("submit", addWebhook);
+    }
+
+    var settingsSelects = document.querySelectorAll(".settings-switcher select");
+    for (var i = 0; i < settingsSelects.length; i++) {
+        settingsSelects[i].addEventListener("change", saveSettings);
+    }
+
     var loginForm = document.querySelector("#login");
     if (loginForm) {
         loginForm.addEventListener("submit", handleLogin);